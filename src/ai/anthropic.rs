@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{build_prompt, parse_expanded, post_json, ExpandedQuery, QueryExpander};
+use crate::http;
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+const DEFAULT_MODEL: &str = "claude-3-5-haiku-latest";
+const API_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 256;
+
+/// Anthropic Messages API
+pub struct AnthropicExpander {
+    api_key: String,
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl AnthropicExpander {
+    pub fn new(api_key: String, base_url: Option<String>, model: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            client: http::client().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    messages: Vec<Message>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[async_trait]
+impl QueryExpander for AnthropicExpander {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    async fn expand(&self, prompt: &str, learning_context: &str) -> Result<ExpandedQuery> {
+        let request = MessagesRequest {
+            model: self.model.clone(),
+            max_tokens: MAX_TOKENS,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: build_prompt(prompt, learning_context),
+            }],
+        };
+
+        let url = format!("{}/messages", self.base_url.trim_end_matches('/'));
+        let builder = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION);
+
+        let response: MessagesResponse = post_json("Anthropic", builder, &request).await?;
+
+        let content = response
+            .content
+            .iter()
+            .find(|block| block.kind == "text")
+            .and_then(|block| block.text.as_deref())
+            .context("No text content in Anthropic response")?;
+
+        parse_expanded(content)
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{build_prompt, parse_expanded, post_json, ExpandedQuery, QueryExpander};
+use crate::http;
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "gemini-2.0-flash";
+
+/// Google Gemini `generateContent` API
+pub struct GeminiExpander {
+    api_key: String,
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl GeminiExpander {
+    pub fn new(api_key: String, base_url: Option<String>, model: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            client: http::client().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiRequest {
+    contents: Vec<Content>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Content {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Part {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponse {
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candidate {
+    content: Content,
+}
+
+#[async_trait]
+impl QueryExpander for GeminiExpander {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    async fn expand(&self, prompt: &str, learning_context: &str) -> Result<ExpandedQuery> {
+        let request = GeminiRequest {
+            contents: vec![Content {
+                parts: vec![Part {
+                    text: build_prompt(prompt, learning_context),
+                }],
+            }],
+        };
+
+        let url = format!(
+            "{}/models/{}:generateContent?key={}",
+            self.base_url.trim_end_matches('/'),
+            self.model,
+            self.api_key
+        );
+
+        let gemini_response: GeminiResponse =
+            post_json("Gemini", self.client.post(&url), &request).await?;
+
+        let content = gemini_response
+            .candidates
+            .first()
+            .context("No response from Gemini")?
+            .content
+            .parts
+            .first()
+            .context("No content in Gemini response")?
+            .text
+            .clone();
+
+        parse_expanded(&content)
+    }
+}
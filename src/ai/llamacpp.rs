@@ -0,0 +1,71 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    build_prompt, expanded_query_schema, parse_expanded, post_json, ExpandedQuery, QueryExpander,
+    LOCAL_MODEL_TIMEOUT,
+};
+use crate::http;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:8080";
+const MAX_TOKENS: u32 = 256;
+
+/// Local llama.cpp server via `/completion`.
+///
+/// The server serves whichever model it was started with, so there is no model setting.
+pub struct LlamaCppExpander {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl LlamaCppExpander {
+    pub fn new(base_url: Option<String>) -> Self {
+        Self {
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            client: http::client().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CompletionRequest {
+    prompt: String,
+    n_predict: u32,
+    temperature: f32,
+    stream: bool,
+    /// Converted to a sampling grammar by the server, so output always matches `ExpandedQuery`
+    json_schema: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionResponse {
+    content: String,
+}
+
+#[async_trait]
+impl QueryExpander for LlamaCppExpander {
+    fn name(&self) -> &'static str {
+        "llamacpp"
+    }
+
+    async fn expand(&self, prompt: &str, learning_context: &str) -> Result<ExpandedQuery> {
+        let request = CompletionRequest {
+            prompt: build_prompt(prompt, learning_context),
+            n_predict: MAX_TOKENS,
+            temperature: 0.2,
+            stream: false,
+            json_schema: expanded_query_schema(),
+        };
+
+        let url = format!("{}/completion", self.base_url.trim_end_matches('/'));
+        let response: CompletionResponse = post_json(
+            "llama.cpp",
+            self.client.post(&url).timeout(LOCAL_MODEL_TIMEOUT),
+            &request,
+        )
+        .await?;
+
+        parse_expanded(&response.content)
+    }
+}
@@ -0,0 +1,300 @@
+mod anthropic;
+mod gemini;
+mod llamacpp;
+mod offline;
+mod ollama;
+mod openai;
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::errors::{self, FetchrError};
+use crate::http::{self, RetryPolicy};
+
+pub use anthropic::AnthropicExpander;
+pub use gemini::GeminiExpander;
+pub use llamacpp::LlamaCppExpander;
+pub use offline::OfflineExpander;
+pub use ollama::OllamaExpander;
+pub use openai::OpenAiExpander;
+
+/// Local models can spend minutes loading before they answer
+const LOCAL_MODEL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Names accepted by `ai.provider` in the config file
+pub const PROVIDERS: &[&str] = &[
+    "gemini",
+    "openai",
+    "anthropic",
+    "ollama",
+    "llamacpp",
+    "offline",
+];
+
+/// Structured response from the LLM with query and image filters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpandedQuery {
+    pub query: String,
+    #[serde(default)]
+    pub img_size: Option<String>,
+    #[serde(default)]
+    pub img_type: Option<String>,
+}
+
+/// An LLM backend that turns a short user input into an optimized search query.
+#[async_trait]
+pub trait QueryExpander: Send + Sync {
+    /// Short name used in config and log output
+    fn name(&self) -> &'static str;
+
+    /// Expand `prompt` into an `ExpandedQuery`, steering the model with `learning_context`
+    async fn expand(&self, prompt: &str, learning_context: &str) -> Result<ExpandedQuery>;
+
+    /// Produce a new query after `previous` failed for the reason described in `hint`
+    async fn reformulate(
+        &self,
+        original: &str,
+        previous: &ExpandedQuery,
+        hint: &str,
+        attempt: u32,
+        learning_context: &str,
+    ) -> Result<ExpandedQuery> {
+        let prompt = format!(
+            "{} (attempt {}: previous query '{}' failed - {})",
+            original, attempt, previous.query, hint
+        );
+        self.expand(&prompt, learning_context).await
+    }
+}
+
+const PROMPT_TEMPLATE: &str = r#"You are an AI Asset Scout. Your task is to take a user's short input and create ONE highly optimized search query with appropriate image filters.
+
+Analyze the input and determine:
+1. What the user wants (logo, product photo, icon, artwork, etc.)
+2. The best single search query that will find a high-quality, relevant image
+3. The appropriate Serper image filters
+
+Available filters:
+- img_size: "large" (high-res photos/products), "medium" (general use), "icon" (small icons/favicons)
+- img_type: "photo" (real photographs), "clipart" (logos, icons, vector-style), "lineart" (simple drawings), "face" (portraits)
+
+Guidelines:
+- For LOGOS/BRANDS: Use img_type "clipart", include "official", "transparent", "vector" or "SVG" in query
+- For PRODUCTS: Use img_type "photo", img_size "large", include "studio", "product shot", "white background"
+- For ICONS: Use img_size "icon" or "medium", img_type "clipart"
+- For PHOTOS/SCENES: Use img_type "photo", img_size "large"
+
+Respond with ONLY a JSON object (no markdown, no extra text):
+{"query": "your optimized search query", "img_size": "large|medium|icon|null", "img_type": "photo|clipart|lineart|face|null"}
+
+Example for "BMW logo":
+{"query": "BMW official logo transparent SVG vector", "img_size": "large", "img_type": "clipart"}
+
+Example for "iPhone 15":
+{"query": "iPhone 15 Pro product photo studio white background", "img_size": "large", "img_type": "photo"}
+"#;
+
+const PROMPT_SUFFIX: &str = "User input: ";
+
+/// Build the expander selected by `ai.provider` in the config file.
+///
+/// `no_ai` forces the offline rules. A missing API key also falls back to them
+/// so fetchr keeps working, with a notice telling the user how to fix it.
+pub fn expander_from_config(config: &Config, no_ai: bool) -> Result<Box<dyn QueryExpander>> {
+    let ai = &config.ai;
+    let name = ai.provider.to_lowercase();
+
+    if no_ai {
+        return Ok(Box::new(OfflineExpander));
+    }
+
+    match name.as_str() {
+        "gemini" => match config.keys.gemini.clone() {
+            Some(api_key) => Ok(Box::new(GeminiExpander::new(
+                api_key,
+                ai.base_url.clone(),
+                ai.model.clone(),
+            ))),
+            None => Ok(missing_key_fallback("Gemini")),
+        },
+        "openai" => Ok(Box::new(OpenAiExpander::new(
+            config.keys.openai.clone(),
+            ai.base_url.clone(),
+            ai.model.clone(),
+        ))),
+        "anthropic" => match config.keys.anthropic.clone() {
+            Some(api_key) => Ok(Box::new(AnthropicExpander::new(
+                api_key,
+                ai.base_url.clone(),
+                ai.model.clone(),
+            ))),
+            None => Ok(missing_key_fallback("Anthropic")),
+        },
+        "ollama" => Ok(Box::new(OllamaExpander::new(
+            ai.base_url.clone(),
+            ai.model.clone(),
+        ))),
+        "llamacpp" | "llama.cpp" => Ok(Box::new(LlamaCppExpander::new(ai.base_url.clone()))),
+        "offline" => Ok(Box::new(OfflineExpander)),
+        _ => anyhow::bail!(
+            "Unknown AI provider: {}. Available: {}",
+            name,
+            PROVIDERS.join(", ")
+        ),
+    }
+}
+
+fn missing_key_fallback(service: &str) -> Box<dyn QueryExpander> {
+    eprintln!(
+        "  \x1b[33m!\x1b[0m {} API key not set, using offline query rules. Run: fetchr config set-key {} <KEY>",
+        service,
+        service.to_lowercase()
+    );
+    Box::new(OfflineExpander)
+}
+
+/// Warn that the LLM failed and the offline rules are being used instead
+fn warn_fallback(expander: &dyn QueryExpander, error: &anyhow::Error) {
+    let reason = error.to_string();
+    eprintln!(
+        "  \x1b[33m!\x1b[0m {} expansion failed ({}), using offline query rules",
+        expander.name(),
+        reason.lines().next().unwrap_or_default()
+    );
+}
+
+/// Whether to carry on with the offline rules after the LLM failed: only when
+/// it answered with something we couldn't use. Auth, quota and connection
+/// errors go back to the caller, which decides whether to stop the run.
+fn use_offline_rules(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<FetchrError>() {
+        Some(FetchrError::BadResponse(_)) => true,
+        Some(_) => false,
+        // Replies that aren't the JSON we asked for
+        None => true,
+    }
+}
+
+/// Try to get learning context from feedback history
+fn learning_context() -> String {
+    crate::feedback::get_learning_context(3)
+        .unwrap_or(None)
+        .unwrap_or_default()
+}
+
+pub async fn expand_prompt(prompt: &str, expander: &dyn QueryExpander) -> Result<ExpandedQuery> {
+    match expander.expand(prompt, &learning_context()).await {
+        Ok(expanded) => Ok(expanded),
+        Err(e) if use_offline_rules(&e) => {
+            warn_fallback(expander, &e);
+            Ok(offline::expand(prompt))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Ask the expander for a new query after a failed attempt, falling back to
+/// offline rules when its reply is unusable
+pub async fn reformulate_prompt(
+    original: &str,
+    previous: &ExpandedQuery,
+    hint: &str,
+    attempt: u32,
+    expander: &dyn QueryExpander,
+) -> Result<ExpandedQuery> {
+    match expander
+        .reformulate(original, previous, hint, attempt, &learning_context())
+        .await
+    {
+        Ok(expanded) => Ok(expanded),
+        Err(e) if use_offline_rules(&e) => {
+            warn_fallback(expander, &e);
+            Ok(offline::reformulate(original, previous, attempt))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Build the full prompt with optional learning context
+fn build_prompt(prompt: &str, learning_context: &str) -> String {
+    format!(
+        "{}{}{}{}",
+        PROMPT_TEMPLATE,
+        learning_context,
+        PROMPT_SUFFIX,
+        prompt
+    )
+}
+
+/// JSON schema for `ExpandedQuery`, for servers that support constrained decoding
+fn expanded_query_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string" },
+            "img_size": { "enum": ["large", "medium", "icon", null] },
+            "img_type": { "enum": ["photo", "clipart", "lineart", "face", null] }
+        },
+        "required": ["query", "img_size", "img_type"]
+    })
+}
+
+/// Parse the model's text reply into an `ExpandedQuery`
+fn parse_expanded(content: &str) -> Result<ExpandedQuery> {
+    // Clean up the response (remove markdown code blocks if present)
+    let content = content
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    // Parse the JSON object from the response
+    if let Ok(expanded) = serde_json::from_str::<ExpandedQuery>(content) {
+        return Ok(expanded);
+    }
+
+    // Chatty models wrap the object in prose; try the outermost {...} span
+    let object = match (content.find('{'), content.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &content[start..=end],
+        _ => content,
+    };
+    let expanded: ExpandedQuery = serde_json::from_str(object)
+        .with_context(|| format!("Failed to parse AI response as JSON: {}", content))?;
+
+    Ok(expanded)
+}
+
+/// POST a JSON body and decode the JSON reply, retrying transient failures
+async fn post_json<Req, Resp>(
+    service: &str,
+    request: reqwest::RequestBuilder,
+    body: &Req,
+) -> Result<Resp>
+where
+    Req: serde::Serialize + Sync,
+    Resp: serde::de::DeserializeOwned,
+{
+    // The builder has no body yet, so cloning it can't fail
+    let response = http::send(service, &RetryPolicy::API, || {
+        request
+            .try_clone()
+            .expect("request without a body is cloneable")
+            .json(body)
+    })
+    .await
+    .map_err(|e| errors::network_error(service, &e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(errors::api_error(service, status, &body).into());
+    }
+
+    let body = response.text().await.map_err(|e| errors::network_error(service, &e))?;
+    serde_json::from_str(&body).map_err(|e| errors::bad_response(service, e).into())
+}
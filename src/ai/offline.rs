@@ -0,0 +1,188 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::{ExpandedQuery, QueryExpander};
+
+/// Deterministic keyword-based expander that needs no API key or network.
+///
+/// Applies the same guidelines as `PROMPT_TEMPLATE`, so results stay close to
+/// what the LLM backends would produce for common inputs.
+pub struct OfflineExpander;
+
+/// What the user is most likely asking for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AssetKind {
+    Logo,
+    Icon,
+    Product,
+    Portrait,
+    LineArt,
+    Scene,
+}
+
+const LOGO_WORDS: &[&str] = &["logo", "logotype", "wordmark", "brand", "emblem", "trademark"];
+const ICON_WORDS: &[&str] = &["icon", "icons", "favicon", "glyph", "emoji", "pictogram"];
+const PRODUCT_WORDS: &[&str] = &[
+    "product", "iphone", "ipad", "macbook", "laptop", "phone", "smartphone", "camera",
+    "headphones", "earbuds", "watch", "sneaker", "sneakers", "shoe", "shoes", "bottle",
+    "console", "controller", "keyboard", "mouse", "monitor", "tablet", "speaker", "bag",
+];
+const PORTRAIT_WORDS: &[&str] = &["portrait", "headshot", "face", "selfie"];
+const LINEART_WORDS: &[&str] = &["drawing", "sketch", "outline", "lineart", "coloring", "doodle"];
+
+fn classify(input: &str) -> AssetKind {
+    let lower = input.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let has_any = |list: &[&str]| words.iter().any(|w| list.contains(w));
+
+    // Order matters: "app icon" is an icon, "Nike logo shoe" is still a logo request
+    if has_any(LOGO_WORDS) {
+        AssetKind::Logo
+    } else if has_any(ICON_WORDS) {
+        AssetKind::Icon
+    } else if has_any(LINEART_WORDS) || lower.contains("line art") {
+        AssetKind::LineArt
+    } else if has_any(PORTRAIT_WORDS) {
+        AssetKind::Portrait
+    } else if has_any(PRODUCT_WORDS) {
+        AssetKind::Product
+    } else {
+        AssetKind::Scene
+    }
+}
+
+/// Append `extra` terms to `input`, skipping any the user already typed
+fn with_terms(input: &str, extra: &[&str]) -> String {
+    let lower = input.to_lowercase();
+    let mut query = input.trim().to_string();
+    for term in extra {
+        if !lower.contains(&term.to_lowercase()) {
+            query.push(' ');
+            query.push_str(term);
+        }
+    }
+    query
+}
+
+fn expanded(query: String, img_size: Option<&str>, img_type: Option<&str>) -> ExpandedQuery {
+    ExpandedQuery {
+        query,
+        img_size: img_size.map(str::to_string),
+        img_type: img_type.map(str::to_string),
+    }
+}
+
+/// Expand a raw user input using keyword rules
+pub fn expand(input: &str) -> ExpandedQuery {
+    match classify(input) {
+        AssetKind::Logo => expanded(
+            with_terms(input, &["official", "logo", "transparent", "vector"]),
+            Some("large"),
+            Some("clipart"),
+        ),
+        AssetKind::Icon => expanded(
+            with_terms(input, &["icon", "transparent"]),
+            Some("icon"),
+            Some("clipart"),
+        ),
+        AssetKind::Product => expanded(
+            with_terms(input, &["product photo", "studio", "white background"]),
+            Some("large"),
+            Some("photo"),
+        ),
+        AssetKind::Portrait => expanded(
+            with_terms(input, &["portrait photo"]),
+            Some("large"),
+            Some("face"),
+        ),
+        AssetKind::LineArt => expanded(
+            with_terms(input, &["line art"]),
+            Some("medium"),
+            Some("lineart"),
+        ),
+        AssetKind::Scene => expanded(
+            with_terms(input, &["high resolution photo"]),
+            Some("large"),
+            Some("photo"),
+        ),
+    }
+}
+
+/// Produce a progressively looser query after `previous` failed
+pub fn reformulate(original: &str, previous: &ExpandedQuery, attempt: u32) -> ExpandedQuery {
+    if attempt <= 2 {
+        // Keep the query but drop the size filter, which is the most common reason for no hits
+        ExpandedQuery {
+            query: previous.query.clone(),
+            img_size: None,
+            img_type: previous.img_type.clone(),
+        }
+    } else {
+        // Last resort: the user's own words with no filters
+        expanded(original.trim().to_string(), None, None)
+    }
+}
+
+#[async_trait]
+impl QueryExpander for OfflineExpander {
+    fn name(&self) -> &'static str {
+        "offline"
+    }
+
+    async fn expand(&self, prompt: &str, _learning_context: &str) -> Result<ExpandedQuery> {
+        Ok(expand(prompt))
+    }
+
+    async fn reformulate(
+        &self,
+        original: &str,
+        previous: &ExpandedQuery,
+        _hint: &str,
+        attempt: u32,
+        _learning_context: &str,
+    ) -> Result<ExpandedQuery> {
+        Ok(reformulate(original, previous, attempt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_by_keyword() {
+        assert_eq!(classify("Nike logo shoe"), AssetKind::Logo);
+        assert_eq!(classify("settings app icon"), AssetKind::Icon);
+        assert_eq!(classify("cat line art"), AssetKind::LineArt);
+        assert_eq!(classify("CEO headshot"), AssetKind::Portrait);
+        assert_eq!(classify("iPhone 15"), AssetKind::Product);
+        assert_eq!(classify("sunset over Lisbon"), AssetKind::Scene);
+        // Whole words only
+        assert_eq!(classify("brandenburg gate"), AssetKind::Scene);
+    }
+
+    #[test]
+    fn expands_without_repeating_the_users_words() {
+        let expanded = expand("Tesla logo");
+        assert_eq!(expanded.query, "Tesla logo official transparent vector");
+        assert_eq!(expanded.img_size.as_deref(), Some("large"));
+        assert_eq!(expanded.img_type.as_deref(), Some("clipart"));
+    }
+
+    #[test]
+    fn reformulate_drops_the_size_then_everything() {
+        let previous = expand("Tesla logo");
+
+        let second = reformulate("Tesla logo", &previous, 2);
+        assert_eq!(second.query, previous.query);
+        assert_eq!(second.img_size, None);
+        assert_eq!(second.img_type.as_deref(), Some("clipart"));
+
+        let last = reformulate("  Tesla logo ", &previous, 3);
+        assert_eq!(last.query, "Tesla logo");
+        assert_eq!((last.img_size, last.img_type), (None, None));
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    build_prompt, parse_expanded, post_json, ExpandedQuery, QueryExpander, LOCAL_MODEL_TIMEOUT,
+};
+use crate::http;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+const DEFAULT_MODEL: &str = "llama3.2";
+
+/// Local Ollama server via `/api/generate`
+pub struct OllamaExpander {
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl OllamaExpander {
+    pub fn new(base_url: Option<String>, model: Option<String>) -> Self {
+        Self {
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            client: http::client().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GenerateRequest {
+    model: String,
+    prompt: String,
+    stream: bool,
+    /// JSON mode: Ollama constrains sampling to valid JSON
+    format: &'static str,
+    options: GenerateOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct GenerateOptions {
+    temperature: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateResponse {
+    response: String,
+}
+
+#[async_trait]
+impl QueryExpander for OllamaExpander {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn expand(&self, prompt: &str, learning_context: &str) -> Result<ExpandedQuery> {
+        let request = GenerateRequest {
+            model: self.model.clone(),
+            prompt: build_prompt(prompt, learning_context),
+            stream: false,
+            format: "json",
+            options: GenerateOptions { temperature: 0.2 },
+        };
+
+        let url = format!("{}/api/generate", self.base_url.trim_end_matches('/'));
+        let response: GenerateResponse = post_json(
+            "Ollama",
+            self.client.post(&url).timeout(LOCAL_MODEL_TIMEOUT),
+            &request,
+        )
+        .await?;
+
+        parse_expanded(&response.response)
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{build_prompt, parse_expanded, post_json, ExpandedQuery, QueryExpander};
+use crate::http;
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+/// OpenAI-compatible `chat/completions` API (OpenAI, Azure proxies, vLLM, LM Studio, ...)
+pub struct OpenAiExpander {
+    api_key: Option<String>,
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl OpenAiExpander {
+    /// `api_key` is optional because many self-hosted compatible servers don't require one
+    pub fn new(api_key: Option<String>, base_url: Option<String>, model: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            client: http::client().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    temperature: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[async_trait]
+impl QueryExpander for OpenAiExpander {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn expand(&self, prompt: &str, learning_context: &str) -> Result<ExpandedQuery> {
+        let request = ChatRequest {
+            model: self.model.clone(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: build_prompt(prompt, learning_context),
+            }],
+            temperature: 0.2,
+        };
+
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let mut builder = self.client.post(&url);
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+
+        let response: ChatResponse = post_json("OpenAI", builder, &request).await?;
+
+        let content = &response
+            .choices
+            .first()
+            .context("No response from OpenAI")?
+            .message
+            .content;
+
+        parse_expanded(content)
+    }
+}
@@ -2,9 +2,11 @@
 // This module will be implemented in the next phase
 
 use anyhow::Result;
-use crate::ai::ExpandedQuery;
-use crate::config::Config;
-use crate::search::ImageResult;
+use serde::Serialize;
+
+use crate::ai::{ExpandedQuery, QueryExpander};
+use crate::download::HostLimiter;
+use crate::search::{Constraints, ImageResult, ImageSearchProvider};
 
 /// Reasons why a search result might be considered a failure
 #[derive(Debug, Clone)]
@@ -15,7 +17,7 @@ pub enum FailureReason {
 }
 
 /// Log entry for auto-mode decisions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AutoDecision {
     pub query: String,
     pub action: String,
@@ -55,10 +57,11 @@ impl AutoSession {
     }
 }
 
-/// Evaluate if an image result meets quality thresholds
-pub fn evaluate_result(result: &ImageResult, _query: &str) -> Option<FailureReason> {
+/// Evaluate if an image result meets quality thresholds and the query's constraints
+pub fn evaluate_result(result: &ImageResult, constraints: &Constraints) -> Option<FailureReason> {
     // Check for minimum dimensions (icons should be at least 32x32, others 100x100)
-    if result.width > 0 && result.height > 0 && (result.width < 32 || result.height < 32) {
+    let tiny = result.width > 0 && result.height > 0 && (result.width < 32 || result.height < 32);
+    if tiny || !constraints.fits(result.width, result.height) {
         return Some(FailureReason::ImageTooSmall {
             width: result.width,
             height: result.height,
@@ -73,7 +76,7 @@ pub async fn reformulate_query(
     previous: &ExpandedQuery,
     failure: &FailureReason,
     attempt: u32,
-    config: &Config,
+    expander: &dyn QueryExpander,
 ) -> Result<ExpandedQuery> {
     // Build a hint based on the failure reason
     let hint = match failure {
@@ -84,83 +87,135 @@ pub async fn reformulate_query(
         }
     };
 
-    // Create a reformulation prompt
-    let reformulation_prompt = format!(
-        "{} (attempt {}: previous query '{}' failed - {})",
-        original, attempt, previous.query, hint
-    );
+    crate::ai::reformulate_prompt(original, previous, &hint, attempt, expander).await
+}
+
+/// Tuning for `find_with_retry`
+#[derive(Debug, Clone)]
+pub struct RetryOptions {
+    /// Search candidates fetched per attempt
+    pub limit: usize,
+    /// Number of distinct images wanted
+    pub count: usize,
+    pub max_retries: u32,
+    /// Filters and minimum dimensions that reformulations must keep
+    pub constraints: Constraints,
+}
 
-    // Use the AI to generate a new query
-    crate::ai::expand_prompt(&reformulation_prompt, config).await
+/// What `find_with_retry` came back with
+#[derive(Debug, Default)]
+pub struct RetryOutcome {
+    /// Chosen images, each with the query that found it
+    pub picks: Vec<(ImageResult, ExpandedQuery)>,
+    /// Every distinct search result seen across all attempts that passed
+    /// `evaluate_result`, so any of them can stand in for a pick
+    pub candidates: Vec<ImageResult>,
 }
 
-/// Find an image with automatic retry on failure
+/// Find up to `options.count` distinct images with automatic retry on failure.
+///
+/// Picks found on earlier attempts are kept, so a reformulated query only has
+/// to make up the shortfall.
 pub async fn find_with_retry(
     query: &str,
-    config: &Config,
-    max_retries: u32,
+    expander: &dyn QueryExpander,
+    provider: &dyn ImageSearchProvider,
+    limiter: &HostLimiter,
+    options: &RetryOptions,
     session: &mut AutoSession,
-    verbose: bool,
-) -> Result<Option<(ImageResult, ExpandedQuery)>> {
+) -> Result<RetryOutcome> {
+    let mut picks: Vec<(ImageResult, ExpandedQuery)> = Vec::new();
+    let mut candidates: Vec<ImageResult> = Vec::new();
     let mut last_expanded: Option<ExpandedQuery> = None;
     let mut last_failure: Option<FailureReason> = None;
 
-    for attempt in 1..=max_retries {
-        if verbose {
-            session.log(query, &format!("attempt {}", attempt), "starting search");
-        }
+    for attempt in 1..=options.max_retries {
+        session.log(
+            query,
+            &format!("attempt {}", attempt),
+            &format!(
+                "expanding with {}, searching via {}",
+                expander.name(),
+                provider.name()
+            ),
+        );
 
         // Expand or reformulate the query
-        let expanded = if attempt == 1 {
-            crate::ai::expand_prompt(query, config).await?
+        let mut expanded = if attempt == 1 {
+            crate::ai::expand_prompt(query, expander).await?
         } else if let (Some(prev), Some(failure)) = (&last_expanded, &last_failure) {
-            reformulate_query(query, prev, failure, attempt, config).await?
+            reformulate_query(query, prev, failure, attempt, expander).await?
         } else {
-            crate::ai::expand_prompt(query, config).await?
+            crate::ai::expand_prompt(query, expander).await?
         };
+        options.constraints.apply(&mut expanded);
 
         // Search for images
-        let results = crate::search::search_images(&expanded, query, 5, config).await?;
+        let results = provider
+            .search_images(&expanded, query, options.limit.max(options.count))
+            .await?;
 
         if results.is_empty() {
             last_failure = Some(FailureReason::NoResults);
             last_expanded = Some(expanded);
-            if verbose {
-                session.log(query, "no results", "will retry with reformulated query");
-            }
+            session.log(query, "no results", "will retry with reformulated query");
             continue;
         }
 
+        for result in &results {
+            if evaluate_result(result, &options.constraints).is_none()
+                && !candidates.iter().any(|c| c.download_url == result.download_url)
+            {
+                candidates.push(result.clone());
+            }
+        }
+
         // Try each result, tracking why we reject them
         let mut had_quality_failure = false;
         let mut quality_failure: Option<FailureReason> = None;
 
         for result in results {
+            if picks.len() >= options.count {
+                break;
+            }
+
+            // Skip images already picked on an earlier attempt
+            if picks.iter().any(|(p, _)| p.download_url == result.download_url) {
+                continue;
+            }
+
             // Check quality
-            if let Some(failure) = evaluate_result(&result, query) {
-                if verbose {
-                    session.log(
-                        query,
-                        "rejected",
-                        &format!("image too small: {}x{}", result.width, result.height),
-                    );
-                }
+            if let Some(failure) = evaluate_result(&result, &options.constraints) {
+                session.log(
+                    query,
+                    "rejected",
+                    &format!("image too small: {}x{}", result.width, result.height),
+                );
                 had_quality_failure = true;
                 quality_failure = Some(failure);
                 continue;
             }
 
-            // Check URL availability
-            if check_url_available(&result.download_url).await {
-                if verbose {
+            // Check the URL serves real image data
+            match crate::download::probe_image(limiter, &result.download_url).await {
+                Ok(_) => {
                     session.log(query, "found", &format!("selected: {}", result.title));
+                    picks.push((result, expanded.clone()));
+                }
+                Err(e) => {
+                    session.log(
+                        query,
+                        "url unavailable",
+                        &format!("{} ({})", result.download_url, e),
+                    );
                 }
-                return Ok(Some((result, expanded)));
-            } else if verbose {
-                session.log(query, "url unavailable", &result.download_url);
             }
         }
 
+        if picks.len() >= options.count {
+            return Ok(RetryOutcome { picks, candidates });
+        }
+
         // Use quality failure if that was the issue, otherwise URLs were the problem
         last_failure = if had_quality_failure {
             quality_failure
@@ -169,27 +224,23 @@ pub async fn find_with_retry(
         };
         last_expanded = Some(expanded);
 
-        if verbose {
-            session.log(query, "all urls failed", "will retry with reformulated query");
-        }
-    }
-
-    if verbose {
         session.log(
             query,
-            "gave up",
-            &format!("after {} attempts", max_retries),
+            &format!("found {}/{}", picks.len(), options.count),
+            "will retry with reformulated query",
         );
     }
 
-    Ok(None)
-}
+    session.log(
+        query,
+        if picks.is_empty() { "gave up" } else { "partial" },
+        &format!(
+            "{}/{} images after {} attempts",
+            picks.len(),
+            options.count,
+            options.max_retries
+        ),
+    );
 
-/// Quick HEAD request to check if a URL is accessible
-async fn check_url_available(url: &str) -> bool {
-    let client = reqwest::Client::new();
-    match client.head(url).send().await {
-        Ok(resp) => resp.status().is_success(),
-        Err(_) => false,
-    }
+    Ok(RetryOutcome { picks, candidates })
 }
@@ -0,0 +1,340 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ai::{ExpandedQuery, QueryExpander};
+use crate::config::{self, Config};
+use crate::search::{ImageResult, ImageSearchProvider};
+
+/// Subdirectory for each kind of cached response
+const EXPAND: &str = "expand";
+const SEARCH: &str = "search";
+const KINDS: &[&str] = &[EXPAND, SEARCH];
+
+/// How a run uses the response cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheMode {
+    /// Serve fresh entries from the cache and store new responses
+    #[default]
+    Use,
+    /// Ignore cached entries but store the new responses (`--refresh`)
+    Refresh,
+    /// Neither read nor write (`--no-cache`)
+    Off,
+}
+
+impl CacheMode {
+    pub fn from_flags(no_cache: bool, refresh: bool) -> Self {
+        if no_cache {
+            CacheMode::Off
+        } else if refresh {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Use
+        }
+    }
+}
+
+/// One cached response, stored as `<kind>/<sha256 of key>.json`
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    created_at: DateTime<Utc>,
+    /// The unhashed key, to tell collisions and stale formats apart
+    key: String,
+    value: T,
+}
+
+/// On-disk cache of LLM expansions and image searches
+pub struct Cache {
+    dir: PathBuf,
+    mode: CacheMode,
+    expand_ttl: Duration,
+    search_ttl: Duration,
+}
+
+impl Cache {
+    pub fn open(config: &Config, mode: CacheMode) -> Result<Arc<Self>> {
+        let mode = if config.cache.enabled { mode } else { CacheMode::Off };
+        Ok(Arc::new(Self {
+            dir: config::cache_dir()?,
+            mode,
+            expand_ttl: Duration::hours(config.cache.expand_ttl_hours as i64),
+            search_ttl: Duration::hours(config.cache.search_ttl_hours as i64),
+        }))
+    }
+
+    fn path(&self, kind: &str, key: &str) -> PathBuf {
+        let hash = Sha256::digest(key.as_bytes());
+        self.dir.join(kind).join(format!("{:x}.json", hash))
+    }
+
+    fn ttl(&self, kind: &str) -> Duration {
+        if kind == EXPAND {
+            self.expand_ttl
+        } else {
+            self.search_ttl
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, kind: &str, key: &str) -> Option<T> {
+        if self.mode != CacheMode::Use {
+            return None;
+        }
+        let content = fs::read_to_string(self.path(kind, key)).ok()?;
+        let entry: Entry<T> = serde_json::from_str(&content).ok()?;
+        (entry.key == key && Utc::now() - entry.created_at < self.ttl(kind)).then_some(entry.value)
+    }
+
+    /// Store `value`; a cache that can't be written just means paying again next time
+    fn put<T: Serialize>(&self, kind: &str, key: &str, value: &T) {
+        if self.mode == CacheMode::Off {
+            return;
+        }
+        let entry = Entry {
+            created_at: Utc::now(),
+            key: key.to_string(),
+            value,
+        };
+        let path = self.path(kind, key);
+        let _ = write_atomic(&path, &entry);
+    }
+}
+
+/// Write via a temporary file so parallel queries never read half an entry
+fn write_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&tmp, serde_json::to_vec(value)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Wrap `inner` so expansions are served from `cache`. The offline rules are
+/// free, so they're never cached.
+pub fn expander(
+    inner: Box<dyn QueryExpander>,
+    config: &Config,
+    cache: &Arc<Cache>,
+) -> Box<dyn QueryExpander> {
+    if cache.mode == CacheMode::Off || inner.name() == "offline" {
+        return inner;
+    }
+    let model = config.ai.model.as_deref().unwrap_or("default");
+    let base_url = config.ai.base_url.as_deref().unwrap_or("default");
+    Box::new(CachedExpander {
+        namespace: format!("{}\0{}\0{}", inner.name(), model, base_url),
+        inner,
+        cache: cache.clone(),
+    })
+}
+
+/// Wrap `inner` so search results are served from `cache`
+pub fn provider(
+    inner: Box<dyn ImageSearchProvider>,
+    cache: &Arc<Cache>,
+) -> Box<dyn ImageSearchProvider> {
+    if cache.mode == CacheMode::Off {
+        return inner;
+    }
+    Box::new(CachedProvider {
+        inner,
+        cache: cache.clone(),
+    })
+}
+
+struct CachedExpander {
+    inner: Box<dyn QueryExpander>,
+    /// Provider, model and endpoint, since each answers differently
+    namespace: String,
+    cache: Arc<Cache>,
+}
+
+#[async_trait]
+impl QueryExpander for CachedExpander {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn expand(&self, prompt: &str, learning_context: &str) -> Result<ExpandedQuery> {
+        let context_hash = Sha256::digest(learning_context.as_bytes());
+        let key = format!("{}\0{}\0{:x}", self.namespace, prompt, context_hash);
+        if let Some(expanded) = self.cache.get(EXPAND, &key) {
+            return Ok(expanded);
+        }
+
+        let expanded = self.inner.expand(prompt, learning_context).await?;
+        self.cache.put(EXPAND, &key, &expanded);
+        Ok(expanded)
+    }
+
+    // Reformulations are attempts to get something *different*, so they always go to the model
+    async fn reformulate(
+        &self,
+        original: &str,
+        previous: &ExpandedQuery,
+        hint: &str,
+        attempt: u32,
+        learning_context: &str,
+    ) -> Result<ExpandedQuery> {
+        self.inner
+            .reformulate(original, previous, hint, attempt, learning_context)
+            .await
+    }
+}
+
+struct CachedProvider {
+    inner: Box<dyn ImageSearchProvider>,
+    cache: Arc<Cache>,
+}
+
+#[async_trait]
+impl ImageSearchProvider for CachedProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn search_images(
+        &self,
+        expanded: &ExpandedQuery,
+        original_query: &str,
+        limit: usize,
+    ) -> Result<Vec<ImageResult>> {
+        let key = format!(
+            "{}\0{}\0{}\0{}\0{}",
+            self.inner.name(),
+            expanded.query,
+            expanded.img_size.as_deref().unwrap_or_default(),
+            expanded.img_type.as_deref().unwrap_or_default(),
+            limit
+        );
+        if let Some(mut results) = self.cache.get::<Vec<ImageResult>>(SEARCH, &key) {
+            // Different inputs can expand to the same search
+            for result in &mut results {
+                result.source_query = original_query.to_string();
+            }
+            return Ok(results);
+        }
+
+        let results = self.inner.search_images(expanded, original_query, limit).await?;
+        self.cache.put(SEARCH, &key, &results);
+        Ok(results)
+    }
+}
+
+/// Entry counts and sizes for `fetchr cache stats`
+#[derive(Debug, Default)]
+struct KindStats {
+    entries: usize,
+    expired: usize,
+    bytes: u64,
+}
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether the entry at `path` is past `ttl` (unreadable entries count as expired)
+fn is_expired(path: &Path, ttl: Duration) -> bool {
+    #[derive(Deserialize)]
+    struct Header {
+        created_at: DateTime<Utc>,
+    }
+
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Header>(&content).ok())
+        .is_none_or(|header| Utc::now() - header.created_at >= ttl)
+}
+
+pub fn stats(config: &Config) -> Result<()> {
+    let cache = Cache::open(config, CacheMode::Use)?;
+    println!("Response cache ({}):", cache.dir.display());
+    if !config.cache.enabled {
+        println!("  (disabled in config)");
+    }
+
+    let mut total = KindStats::default();
+    for kind in KINDS {
+        let mut stats = KindStats::default();
+        for path in entries(&cache.dir.join(kind)) {
+            stats.entries += 1;
+            stats.bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if is_expired(&path, cache.ttl(kind)) {
+                stats.expired += 1;
+            }
+        }
+        println!(
+            "  {:<8} {:>5} entries, {:>4} expired, {} (ttl {}h)",
+            kind,
+            stats.entries,
+            stats.expired,
+            format_bytes(stats.bytes),
+            cache.ttl(kind).num_hours()
+        );
+        total.entries += stats.entries;
+        total.expired += stats.expired;
+        total.bytes += stats.bytes;
+    }
+    println!(
+        "  {:<8} {:>5} entries, {:>4} expired, {}",
+        "total",
+        total.entries,
+        total.expired,
+        format_bytes(total.bytes)
+    );
+    Ok(())
+}
+
+/// Delete every cached response
+pub fn clear() -> Result<usize> {
+    let dir = config::cache_dir()?;
+    let mut removed = 0;
+    for kind in KINDS {
+        for path in entries(&dir.join(kind)) {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Delete cached responses older than their TTL
+pub fn prune(config: &Config) -> Result<usize> {
+    let cache = Cache::open(config, CacheMode::Use)?;
+    let mut removed = 0;
+    for kind in KINDS {
+        for path in entries(&cache.dir.join(kind)) {
+            if is_expired(&path, cache.ttl(kind)) {
+                fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
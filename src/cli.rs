@@ -0,0 +1,1223 @@
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use clap::{Parser, Subcommand};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::fetcher::{Event, Progress};
+use crate::input::{self, QuerySpec};
+use crate::{ai, auto, cache, config, download, feedback, http, manifest, mcp, output, preview, search, serve, tape};
+use crate::{Fetcher, Pick};
+
+const VERSION: &str = "1.0";
+const AUTHOR: &str = "Aaron Aalto";
+
+const BANNER: &str = r#"
+    ███████╗███████╗████████╗ ██████╗██╗  ██╗██████╗
+    ██╔════╝██╔════╝╚══██╔══╝██╔════╝██║  ██║██╔══██╗
+    █████╗  █████╗     ██║   ██║     ███████║██████╔╝
+    ██╔══╝  ██╔══╝     ██║   ██║     ██╔══██║██╔══██╗
+    ██║     ███████╗   ██║   ╚██████╗██║  ██║██║  ██║
+    ╚═╝     ╚══════╝   ╚═╝    ╚═════╝╚═╝  ╚═╝╚═╝  ╚═╝
+"#;
+
+fn print_banner() {
+    println!("\x1b[36m{}\x1b[0m", BANNER);
+    println!(
+        "    \x1b[90mv{} · Created by {}\x1b[0m",
+        VERSION, AUTHOR
+    );
+    println!("    \x1b[90mRetrieve multiple assets at once\x1b[0m\n");
+}
+
+#[derive(Parser)]
+#[command(name = "fetchr")]
+#[command(about = "AI-powered image asset fetcher - retrieve multiple assets at once")]
+#[command(version = VERSION)]
+struct Cli {
+    /// Asset descriptions - comma-separated (no quotes needed)
+    /// Example: fetchr Tesla logo, Apple icon, Nike swoosh
+    #[arg(trailing_var_arg = true)]
+    queries: Vec<String>,
+
+    /// Read queries from a file: text (comma/newline separated), CSV, JSON or YAML; "-" reads stdin
+    #[arg(short = 'f', long = "file")]
+    file: Option<PathBuf>,
+
+    /// Format of --file or piped stdin (default: from the extension, else text)
+    #[arg(long, value_enum)]
+    input_format: Option<input::InputFormat>,
+
+    /// Output directory (default: defaults.output_dir from config, then Downloads/fetchr)
+    #[arg(short = 'o', long = "output")]
+    output: Option<PathBuf>,
+
+    /// Filename template, e.g. "{query}" or "{date}/{index}-{domain}" (default: download.name_template)
+    #[arg(long)]
+    name_template: Option<String>,
+
+    /// What to do when a file already exists (default: download.on_conflict)
+    #[arg(long, value_enum)]
+    on_conflict: Option<download::ConflictPolicy>,
+
+    /// Images to download per query; override per query with a trailing @N, e.g. "Tesla logo @3"
+    #[arg(short = 'n', long, default_value = "1")]
+    count: usize,
+
+    /// Review all candidates per query and choose which to download
+    #[arg(long, conflicts_with = "auto")]
+    pick: bool,
+
+    /// Show thumbnails inline: auto, kitty, iterm, sixel, blocks or off (bare flag = auto)
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "auto")]
+    preview: Option<preview::PreviewMode>,
+
+    /// Output format: text, or json/ndjson for scripts (implies --yes, no banner or spinners)
+    #[arg(long, value_enum, default_value = "text")]
+    format: output::OutputFormat,
+
+    /// Write the single downloaded image to stdout instead of a file
+    #[arg(long, conflicts_with_all = ["output", "name_template", "on_conflict", "pick", "rate", "preview"])]
+    stdout: bool,
+
+    /// Number of queries to process in parallel
+    #[arg(short = 'j', long, default_value = "4")]
+    jobs: usize,
+
+    /// Exit non-zero when any query fails, only when all fail, or never
+    #[arg(long, value_enum, default_value = "all")]
+    fail_on: output::FailOn,
+
+    /// Write a JSON report of every query, decision and download to this file
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Skip confirmation prompts (automatic when stdin is not a terminal)
+    #[arg(short = 'y', long)]
+    yes: bool,
+
+    /// Prompt for ratings after download
+    #[arg(long)]
+    rate: bool,
+
+    /// Autonomous mode: skip confirmations, auto-retry on failure
+    #[arg(long)]
+    auto: bool,
+
+    /// Maximum query reformulations in auto mode (default: 3)
+    #[arg(long, default_value = "3")]
+    max_retries: u32,
+
+    /// Verbose logging (show AI decisions and retry attempts)
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Skip the LLM and expand queries with built-in offline rules
+    #[arg(long, global = true)]
+    no_ai: bool,
+
+    /// Image search provider (default: search.provider from config)
+    #[arg(long, global = true)]
+    provider: Option<String>,
+
+    /// Don't read or write the response cache
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    no_cache: bool,
+
+    /// Ignore cached expansions and searches, but store the fresh responses
+    #[arg(long, global = true)]
+    refresh: bool,
+
+    /// Save every HTTP request and response to this directory, with API keys redacted
+    #[arg(long, value_name = "DIR", global = true, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer HTTP requests from a directory made by --record instead of the network
+    #[arg(long, value_name = "DIR", global = true)]
+    replay: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Manage configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Manage feedback history
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+    /// Manage the cache of LLM expansions and search results
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Fetch the assets listed in fetchr.toml that are missing, and update fetchr.lock
+    Sync {
+        /// Re-resolve these assets even if they are locked
+        #[arg(long, value_name = "NAME", num_args = 1..)]
+        update: Vec<String>,
+
+        /// Manifest path (default: nearest fetchr.toml)
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+    },
+    /// Run a local HTTP API (POST /find, POST /download, GET /history, POST /feedback)
+    Serve {
+        /// Address to listen on; keep it local, the API has no authentication
+        #[arg(long, default_value = "127.0.0.1")]
+        host: std::net::IpAddr,
+
+        #[arg(long, default_value = "7420")]
+        port: u16,
+
+        /// Directory downloads are confined to (default: defaults.output_dir from config, then Downloads/fetchr)
+        #[arg(long)]
+        root: Option<PathBuf>,
+    },
+    /// Run a Model Context Protocol server on stdio, for coding assistants
+    Mcp,
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Show feedback statistics
+    Stats,
+    /// Clear all feedback history
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show entry counts, sizes and TTLs
+    Stats,
+    /// Delete every cached response
+    Clear,
+    /// Delete cached responses older than their TTL
+    Prune,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Set an API key
+    SetKey {
+        /// Provider name (gemini, serper)
+        provider: String,
+        /// API key value
+        key: String,
+    },
+    /// Show current configuration
+    Show,
+}
+
+/// Options for the find command
+#[derive(Clone)]
+struct FindOptions {
+    yes: bool,
+    rate: bool,
+    auto_mode: bool,
+    max_retries: u32,
+    verbose: bool,
+    no_ai: bool,
+    provider: Option<String>,
+    output: Option<PathBuf>,
+    name_template: Option<String>,
+    on_conflict: Option<download::ConflictPolicy>,
+    count: usize,
+    pick: bool,
+    preview: Option<preview::Protocol>,
+    format: output::OutputFormat,
+    /// Stream the image to stdout (`--stdout`)
+    to_stdout: bool,
+    /// Queries processed in parallel
+    jobs: usize,
+    fail_on: output::FailOn,
+    cache: cache::CacheMode,
+}
+
+/// Entry point of the `fetchr` binary; not part of the library API
+pub async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    if let Some(dir) = &cli.record {
+        tape::record(dir)?;
+    }
+    if let Some(dir) = &cli.replay {
+        tape::replay(dir)?;
+    }
+    let cache = cache_mode(&cli);
+
+    match cli.command {
+        Some(Commands::Config { action }) => match action {
+            ConfigAction::SetKey { provider, key } => {
+                config::set_key(&provider, &key)?;
+                println!("Saved {} key", provider);
+            }
+            ConfigAction::Show => {
+                config::show()?;
+            }
+        },
+        Some(Commands::History { action }) => match action {
+            HistoryAction::Stats => {
+                let (up, down, skip) = feedback::get_stats()?;
+                println!("Feedback history:");
+                println!("  Thumbs up:   {}", up);
+                println!("  Thumbs down: {}", down);
+                println!("  Skipped:     {}", skip);
+                println!("  Total:       {}", up + down + skip);
+            }
+            HistoryAction::Clear => {
+                feedback::clear_history()?;
+                println!("Feedback history cleared.");
+            }
+        },
+        Some(Commands::Cache { action }) => match action {
+            CacheAction::Stats => {
+                cache::stats(&config::load()?)?;
+            }
+            CacheAction::Clear => {
+                let removed = cache::clear()?;
+                println!("Removed {} cached response{}.", removed, if removed == 1 { "" } else { "s" });
+            }
+            CacheAction::Prune => {
+                let removed = cache::prune(&config::load()?)?;
+                println!("Removed {} expired response{}.", removed, if removed == 1 { "" } else { "s" });
+            }
+        },
+        Some(Commands::Sync { update, manifest }) => {
+            manifest::sync(&manifest::SyncOptions {
+                manifest,
+                update,
+                no_ai: cli.no_ai,
+                provider: cli.provider.clone(),
+                cache,
+            })
+            .await?;
+        }
+        Some(Commands::Serve { host, port, root }) => {
+            serve::run(serve::ServeOptions {
+                addr: (host, port).into(),
+                root,
+                no_ai: cli.no_ai,
+                provider: cli.provider.clone(),
+                cache,
+            })
+            .await?;
+        }
+        Some(Commands::Mcp) => {
+            mcp::run(mcp::McpOptions {
+                no_ai: cli.no_ai,
+                provider: cli.provider.clone(),
+                cache,
+            })
+            .await?;
+        }
+        None => {
+            let structured = cli.format.is_structured();
+            // With a pipe on stdin there is nobody to answer prompts
+            let interactive = io::stdin().is_terminal();
+            if !structured && !cli.stdout {
+                print_banner();
+            }
+
+            if cli.stdout && structured {
+                anyhow::bail!("--stdout can't be combined with --format json/ndjson");
+            }
+            if cli.pick && !interactive {
+                anyhow::bail!("--pick needs a terminal on stdin");
+            }
+            if cli.rate && !interactive {
+                eprintln!("  \x1b[33m!\x1b[0m stdin is not a terminal, skipping --rate");
+            }
+
+            let opts = FindOptions {
+                // auto mode, scripts and pipelines imply yes
+                yes: cli.yes || cli.auto || structured || cli.stdout || !interactive,
+                rate: cli.rate && interactive,
+                auto_mode: cli.auto,
+                max_retries: cli.max_retries,
+                verbose: cli.verbose,
+                no_ai: cli.no_ai,
+                provider: cli.provider.clone(),
+                output: cli.output.clone(),
+                name_template: cli.name_template.clone(),
+                on_conflict: cli.on_conflict,
+                count: cli.count.max(1),
+                pick: cli.pick,
+                preview: if structured || cli.stdout {
+                    None
+                } else {
+                    cli.preview.and_then(preview::resolve)
+                },
+                format: cli.format,
+                to_stdout: cli.stdout,
+                jobs: cli.jobs.max(1),
+                fail_on: cli.fail_on,
+                cache,
+            };
+
+            let started_at = Utc::now();
+            let result = if structured {
+                run_structured(&cli, &opts).await
+            } else {
+                run_text(&cli, &opts, interactive).await
+            };
+            let status = match &result {
+                Ok(run) => run.status(opts.fail_on),
+                Err(e) => output::ExitStatus::from_error(e),
+            };
+
+            if let Some(path) = &cli.report {
+                let report = output::Report {
+                    started_at,
+                    finished_at: Utc::now(),
+                    status,
+                    exit_code: status.code(),
+                    output_dir: result.as_ref().ok().and_then(|run| run.output_dir.as_deref()),
+                    queries: result.as_ref().map(|run| run.records.as_slice()).unwrap_or_default(),
+                    error: result.as_ref().err().map(output::ErrorRecord::from_error),
+                };
+                if let Err(e) = output::write_report(path, &report) {
+                    eprintln!("  \x1b[33m!\x1b[0m {:#}", e);
+                }
+            }
+
+            if let Err(e) = &result {
+                if structured {
+                    // Report failures as JSON too, so scripts never have to parse prose
+                    output::emit_fatal(cli.format, e);
+                } else {
+                    eprintln!("Error: {:?}", e);
+                }
+            }
+            if status != output::ExitStatus::Success {
+                std::process::exit(status.code());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect queries from file, CLI args, stdin, or interactive mode
+async fn run_text(cli: &Cli, opts: &FindOptions, interactive: bool) -> Result<Run> {
+    let queries = collect_queries(cli)?;
+
+    if !queries.is_empty() {
+        cmd_find(&queries, opts).await
+    } else if interactive && !cli.stdout {
+        interactive_mode(opts).await
+    } else {
+        anyhow::bail!("No queries given; pass them as arguments, with --file or on stdin");
+    }
+}
+
+/// Recording or replaying traffic needs every request to really be made, so it bypasses the cache
+fn cache_mode(cli: &Cli) -> cache::CacheMode {
+    let taped = cli.record.is_some() || cli.replay.is_some();
+    cache::CacheMode::from_flags(cli.no_cache || taped, cli.refresh)
+}
+
+/// Collect queries from `--file`, the command line, or stdin when it's a pipe
+fn collect_queries(cli: &Cli) -> Result<Vec<QuerySpec>> {
+    if let Some(file_path) = &cli.file {
+        input::parse_queries_from_file(file_path, cli.input_format)
+    } else if !cli.queries.is_empty() {
+        // Join all args and split by comma (no quotes needed)
+        Ok(input::parse_comma_separated(&cli.queries.join(" "))
+            .iter()
+            .map(|q| QuerySpec::parse(q))
+            .collect())
+    } else if !io::stdin().is_terminal() {
+        input::parse_queries_from_stdin(cli.input_format)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// `--format json|ndjson`: no prompts of any kind, so queries must be given up front
+async fn run_structured(cli: &Cli, opts: &FindOptions) -> Result<Run> {
+    if opts.pick || opts.rate {
+        anyhow::bail!("--pick and --rate are interactive and can't be combined with --format json/ndjson");
+    }
+
+    let queries = collect_queries(cli)?;
+    if queries.is_empty() {
+        anyhow::bail!("No queries given; pass them as arguments or with --file");
+    }
+
+    cmd_find(&queries, opts).await
+}
+
+async fn interactive_mode(base: &FindOptions) -> Result<Run> {
+    println!("  \x1b[1mEnter assets to fetch (comma-separated):\x1b[0m");
+    print!("  \x1b[36m>\x1b[0m ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    let queries: Vec<QuerySpec> = input::parse_comma_separated(&input)
+        .iter()
+        .map(|q| QuerySpec::parse(q))
+        .collect();
+
+    if queries.is_empty() {
+        println!("\n  No valid queries entered (min 2 characters each). Exiting.");
+        return Ok(Run::default());
+    }
+
+    let opts = FindOptions {
+        yes: false,
+        rate: false,
+        auto_mode: false,
+        max_retries: 3,
+        verbose: false,
+        ..base.clone()
+    };
+
+    println!();
+    cmd_find(&queries, &opts).await
+}
+
+/// " (+2 more)" style suffix, or a shortfall note when fewer than wanted were found
+fn more_suffix(found: usize, wanted: usize) -> String {
+    if wanted <= 1 || found == 0 {
+        String::new()
+    } else if found < wanted {
+        format!(" \x1b[33m({}/{} found)\x1b[0m", found, wanted)
+    } else {
+        format!(" \x1b[90m(+{} more)\x1b[0m", found - 1)
+    }
+}
+
+/// A spinner with a message; hidden when `quiet` so structured output stays clean
+fn create_spinner(msg: &str, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    start_spinner(ProgressBar::new_spinner(), msg)
+}
+
+/// Style a spinner that is already attached to its draw target and start it ticking
+fn start_spinner(spinner: ProgressBar, msg: &str) -> ProgressBar {
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message(msg.to_string());
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+fn format_dimensions(width: u32, height: u32) -> String {
+    if width > 0 && height > 0 {
+        format!("{}x{}", width, height)
+    } else {
+        "unknown".to_string()
+    }
+}
+
+/// Shorten to at most `max_len` characters, counting characters rather than
+/// bytes so titles in other scripts are cut on a character boundary
+fn truncate_title(title: &str, max_len: usize) -> String {
+    if title.chars().count() <= max_len {
+        title.to_string()
+    } else {
+        let kept: String = title.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", kept)
+    }
+}
+
+/// Replace home directory with ~ for cleaner display
+fn shorten_path(path: &std::path::Path) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(relative) = path.strip_prefix(&home) {
+            return format!("~/{}", relative.display());
+        }
+    }
+    path.display().to_string()
+}
+
+/// Truncate URL for display: show domain + abbreviated path
+fn truncate_url(url: &str, max_len: usize) -> String {
+    if url.len() <= max_len {
+        return url.to_string();
+    }
+
+    // Try to parse and show domain + truncated path
+    if let Some(domain_end) = url.find("://").map(|i| i + 3) {
+        if let Some(path_start) = url[domain_end..].find('/') {
+            let domain = &url[..domain_end + path_start];
+            let path = &url[domain_end + path_start..];
+
+            let available = max_len.saturating_sub(domain.len() + 4); // 4 for "/..."
+            if available > 8 && path.len() > available {
+                // Show last part of path
+                let suffix = &path[path.len().saturating_sub(available)..];
+                return format!("{}/...{}", domain, suffix);
+            }
+        }
+    }
+
+    // Fallback: just truncate
+    format!("{}...", &url[..max_len.saturating_sub(3)])
+}
+
+/// An image chosen for download, with what the CLI shows alongside it
+struct DownloadedImageInfo {
+    found: Pick,
+    /// 1-based rank among the images picked for the same query
+    pick: usize,
+    /// Number of images picked for the same query
+    picks: usize,
+    /// Rendered thumbnail, when previews are enabled
+    preview: Option<String>,
+    /// Index of the query this image was found for
+    record: usize,
+}
+
+impl DownloadedImageInfo {
+    fn new(found: Pick) -> Self {
+        Self {
+            found,
+            pick: 1,
+            picks: 1,
+            preview: None,
+            record: 0,
+        }
+    }
+}
+
+/// All search results for one query, awaiting the user's choice in `--pick` mode
+struct PendingPick {
+    record: usize,
+    query: String,
+    expanded: ai::ExpandedQuery,
+    candidates: Vec<search::ImageResult>,
+}
+
+/// Number the picks for one query once we know how many there are
+fn number_picks(picks: &mut [DownloadedImageInfo], record: usize) {
+    let total = picks.len();
+    for (i, info) in picks.iter_mut().enumerate() {
+        info.pick = i + 1;
+        info.picks = total;
+        info.record = record;
+    }
+}
+
+/// Draws a spinner line per query from the fetcher's progress, plus a second
+/// one for the search once the query has been expanded
+struct QuerySpinners {
+    multi: MultiProgress,
+    auto_mode: bool,
+    max_retries: u32,
+    /// Each query's current line and the number of images it wants
+    lines: Mutex<HashMap<usize, (ProgressBar, usize)>>,
+}
+
+impl QuerySpinners {
+    fn update(&self, progress: &Progress) {
+        let step = format!("[{}/{}]", progress.index + 1, progress.total);
+        let query = truncate_title(&progress.query, 30);
+        let mut lines = self.lines.lock().unwrap();
+        let current = lines.get(&progress.index).cloned();
+
+        match (&progress.event, current) {
+            (Event::Started { wanted }, _) => {
+                let msg = if self.auto_mode {
+                    let images = if *wanted > 1 {
+                        format!(" ({} images)", wanted)
+                    } else {
+                        String::new()
+                    };
+                    format!("{} Auto-searching \"{}\"{}...", step, query, images)
+                } else {
+                    format!("{} Optimizing \"{}\"...", step, query)
+                };
+                let spinner = start_spinner(self.multi.add(ProgressBar::new_spinner()), &msg);
+                lines.insert(progress.index, (spinner, *wanted));
+            }
+            (Event::Skipped, _) => {
+                let spinner = start_spinner(self.multi.add(ProgressBar::new_spinner()), "");
+                spinner.finish_with_message(format!("\x1b[90m-\x1b[0m {} Skipped \"{}\"", step, query));
+            }
+            (Event::Expanded(expanded), Some((spinner, wanted))) => {
+                let filter_info = match (&expanded.img_size, &expanded.img_type) {
+                    (Some(s), Some(t)) => format!(" [{}:{}]", s, t),
+                    (Some(s), None) => format!(" [{}]", s),
+                    (None, Some(t)) => format!(" [{}]", t),
+                    (None, None) => String::new(),
+                };
+                spinner.finish_with_message(format!(
+                    "\x1b[32m✓\x1b[0m {} Query: \"{}\"{}",
+                    step,
+                    truncate_title(&expanded.query, 40),
+                    filter_info
+                ));
+                // The second line goes right below the first so each query's lines stay together
+                let search = start_spinner(
+                    self.multi.insert_after(&spinner, ProgressBar::new_spinner()),
+                    &format!("{} Finding best match{}...", step, if wanted > 1 { "es" } else { "" }),
+                );
+                lines.insert(progress.index, (search, wanted));
+            }
+            (Event::Retrying { service }, Some((spinner, _))) => {
+                spinner.set_message(if self.auto_mode {
+                    format!("{} {} unavailable, retrying \"{}\"...", step, service, query)
+                } else {
+                    format!("{} {} unavailable, retrying...", step, service)
+                });
+            }
+            (Event::Checking { candidates }, Some((spinner, _))) => {
+                spinner.set_message(format!(
+                    "{} Checking {} candidate{}...",
+                    step,
+                    candidates,
+                    if *candidates == 1 { "" } else { "s" }
+                ));
+            }
+            (Event::Candidates { count: n }, Some((spinner, _))) => {
+                spinner.finish_with_message(format!(
+                    "\x1b[32m✓\x1b[0m {} {} candidate{} for \"{}\"",
+                    step,
+                    n,
+                    if *n == 1 { "" } else { "s" },
+                    query
+                ));
+            }
+            (Event::Found { title, found, wanted }, Some((spinner, _))) => {
+                spinner.finish_with_message(format!(
+                    "\x1b[32m✓\x1b[0m {} Found: {}{}",
+                    step,
+                    truncate_title(title, 45),
+                    more_suffix(*found, *wanted)
+                ));
+            }
+            (Event::NoResults, Some((spinner, _))) => {
+                let retries = if self.auto_mode {
+                    format!(" (after {} retries)", self.max_retries)
+                } else {
+                    String::new()
+                };
+                spinner.finish_with_message(format!(
+                    "\x1b[33m!\x1b[0m {} No results for \"{}\"{}",
+                    step, query, retries
+                ));
+            }
+            (Event::Failed { error }, Some((spinner, _))) => {
+                spinner.finish_with_message(format!(
+                    "\x1b[31m✗\x1b[0m {} Error for \"{}\": {}",
+                    step, query, error
+                ));
+            }
+            (_, None) => {}
+        }
+    }
+}
+
+/// Set up the library's fetcher from the command line options
+fn build_fetcher(opts: &FindOptions, multi: &MultiProgress, quiet: bool) -> Result<Fetcher> {
+    let mut builder = Fetcher::builder()
+        .no_ai(opts.no_ai)
+        .cache(opts.cache)
+        .count(opts.count)
+        .auto(opts.auto_mode)
+        .max_retries(opts.max_retries)
+        .candidates_only(opts.pick)
+        .jobs(opts.jobs)
+        .progress_bars(!quiet);
+    if let Some(name) = &opts.provider {
+        builder = builder.provider_name(name.clone());
+    }
+    if let Some(dir) = &opts.output {
+        builder = builder.output_dir(dir);
+    }
+    if let Some(template) = &opts.name_template {
+        builder = builder.name_template(template.clone());
+    }
+    if let Some(policy) = opts.on_conflict {
+        builder = builder.on_conflict(policy);
+    }
+    if !quiet {
+        let spinners = QuerySpinners {
+            multi: multi.clone(),
+            auto_mode: opts.auto_mode,
+            max_retries: opts.max_retries,
+            lines: Mutex::new(HashMap::new()),
+        };
+        builder = builder.on_progress(move |progress| spinners.update(progress));
+    }
+    builder.build()
+}
+
+async fn cmd_find(queries: &[QuerySpec], opts: &FindOptions) -> Result<Run> {
+    if opts.to_stdout {
+        let total: usize = queries.iter().map(|q| q.count.unwrap_or(opts.count)).sum();
+        if total != 1 {
+            anyhow::bail!("--stdout writes exactly one image; give a single query without --count or @N");
+        }
+        if io::stdout().is_terminal() {
+            anyhow::bail!("Refusing to write image data to a terminal; redirect stdout to a file or pipe");
+        }
+    }
+
+    let quiet = opts.format.is_structured() || opts.to_stdout;
+    let multi = if quiet {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
+    let fetcher = build_fetcher(opts, &multi, quiet)?;
+    // Nothing is saved with --stdout, so don't require a Downloads folder
+    let output_dir = if opts.to_stdout {
+        PathBuf::new()
+    } else {
+        fetcher.output_dir()?
+    };
+
+    // Show queries and confirm before searching (API calls cost money)
+    if !quiet {
+        println!(
+            "  \x1b[1mReady to search for {} asset{}:\x1b[0m\n",
+            queries.len(),
+            if queries.len() == 1 { "" } else { "s" }
+        );
+
+        for (i, spec) in queries.iter().enumerate() {
+            let count = spec.count.unwrap_or(opts.count);
+            if count > 1 {
+                println!(
+                    "  \x1b[36m{:>2}.\x1b[0m {} \x1b[90m(×{})\x1b[0m",
+                    i + 1,
+                    spec.query,
+                    count
+                );
+            } else {
+                println!("  \x1b[36m{:>2}.\x1b[0m {}", i + 1, spec.query);
+            }
+        }
+        println!();
+    }
+
+    if !opts.yes {
+        print!("  Proceed with search? \x1b[90m[Y/n]\x1b[0m ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let input = input.trim().to_lowercase();
+
+        if !input.is_empty() && input != "y" && input != "yes" {
+            println!("\n  Cancelled.");
+            return Ok(Run {
+                cancelled: true,
+                ..Run::default()
+            });
+        }
+        println!();
+    }
+
+    // Expand → search → availability for up to `jobs` queries at once, in input order
+    let found = fetcher.find_many(queries).await;
+
+    let mut all_results: Vec<DownloadedImageInfo> = Vec::new();
+    let mut pending: Vec<PendingPick> = Vec::new();
+    let mut auto_session = auto::AutoSession::new();
+    let mut records: Vec<output::QueryRecord> = Vec::new();
+    for (i, result) in found.into_iter().enumerate() {
+        records.push(result.record());
+        auto_session.decisions.extend(result.decisions);
+        if let (true, None, Some(expanded)) = (opts.pick, &result.error, result.expanded) {
+            pending.push(PendingPick {
+                record: i,
+                query: result.query,
+                expanded,
+                candidates: result.candidates,
+            });
+        }
+        let mut picks: Vec<DownloadedImageInfo> =
+            result.picks.into_iter().map(DownloadedImageInfo::new).collect();
+        number_picks(&mut picks, i);
+        all_results.extend(picks);
+    }
+
+    // Show auto-mode decision log if verbose
+    if opts.auto_mode && opts.verbose && !quiet {
+        auto_session.print_summary();
+    }
+
+    // Let the user choose among candidates
+    if opts.pick {
+        println!();
+        for pick in &pending {
+            all_results.extend(pick_candidates(pick)?);
+        }
+    }
+
+    for info in &all_results {
+        records[info.record]
+            .chosen
+            .push(output::ChosenImage::new(info.found.image.clone()));
+    }
+
+    // Step 3: Display results summary
+    if all_results.is_empty() {
+        if opts.to_stdout {
+            anyhow::bail!("No image found for \"{}\"", queries[0].query);
+        }
+        if quiet {
+            output::emit(opts.format, &output_dir, &records)?;
+        } else {
+            println!("\n  No images found.");
+        }
+        return Ok(finish_batch(records, output_dir, opts, quiet));
+    }
+
+    if opts.to_stdout {
+        let url = &all_results[0].found.image.download_url;
+        let policy = http::RetryPolicy::download(fetcher.config().download.max_retries);
+        download::stream_image(url, &policy, &mut io::stdout().lock()).await?;
+        records[all_results[0].record].chosen[0].status = output::ImageStatus::Saved;
+        return Ok(Run {
+            records,
+            ..Run::default()
+        });
+    }
+
+    if !quiet {
+        println!("\n  \x1b[1mFound {} image{}:\x1b[0m\n", all_results.len(), if all_results.len() == 1 { "" } else { "s" });
+    }
+
+    if let Some(protocol) = opts.preview {
+        load_previews(&mut all_results, protocol, fetcher.limiter()).await;
+    }
+
+    if !quiet {
+        for (i, info) in all_results.iter().enumerate() {
+            let image = &info.found.image;
+            let variant = if info.picks > 1 {
+                format!(" \x1b[90m({}/{})\x1b[0m", info.pick, info.picks)
+            } else {
+                String::new()
+            };
+            println!(
+                "  \x1b[36m{:>2}.\x1b[0m \x1b[1m{}\x1b[0m{}",
+                i + 1,
+                truncate_title(&image.source_query, 50),
+                variant
+            );
+            println!(
+                "      {} · \x1b[4m{}\x1b[0m",
+                format_dimensions(image.width, image.height),
+                truncate_url(&image.download_url, 60)
+            );
+            if let Some(preview) = &info.preview {
+                println!("{}", preview);
+            }
+            println!();
+        }
+    }
+
+    // Step 4: Confirm download (already done per image when picking)
+    let should_download = if opts.yes || opts.pick {
+        true
+    } else {
+        print!("  Download all? \x1b[90m[Y/n]\x1b[0m ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let input = input.trim().to_lowercase();
+
+        input.is_empty() || input == "y" || input == "yes"
+    };
+
+    if !should_download {
+        println!("\n  Cancelled.");
+        return Ok(Run {
+            output_dir: Some(output_dir),
+            records,
+            cancelled: true,
+        });
+    }
+
+    // Step 5: Download to the resolved output directory
+    if !quiet {
+        println!();
+    }
+    let items: Vec<download::DownloadItem> = all_results
+        .iter()
+        .map(|info| {
+            let record = &records[info.record];
+            // Images chosen with --pick are never swapped for another candidate
+            let fallbacks = if opts.pick {
+                Vec::new()
+            } else {
+                record
+                    .candidates
+                    .iter()
+                    .filter(|c| !record.chosen.iter().any(|p| p.image.download_url == c.download_url))
+                    .cloned()
+                    .collect()
+            };
+            download::DownloadItem {
+                image: info.found.image.clone(),
+                pick: info.pick,
+                picks: info.picks,
+                name_template: queries[info.record].filename.clone(),
+                fallbacks,
+                index: None,
+            }
+        })
+        .collect();
+    let downloaded = fetcher.download_to(&items, &output_dir).await?;
+
+    let mut saved = 0;
+    for (info, image) in all_results.iter_mut().zip(downloaded) {
+        if image.status == output::ImageStatus::Saved {
+            saved += 1;
+        }
+        if let Some(chosen) = records[info.record]
+            .chosen
+            .iter_mut()
+            .find(|c| c.image.download_url == info.found.image.download_url)
+        {
+            // A fallback replaced the pick, so ratings should go to what was saved
+            if image.status != output::ImageStatus::Failed {
+                info.found.image = image.image.clone();
+            }
+            *chosen = image;
+        }
+    }
+
+    if quiet {
+        output::emit(opts.format, &output_dir, &records)?;
+        return Ok(finish_batch(records, output_dir, opts, quiet));
+    }
+
+    println!("\n  \x1b[32m✓\x1b[0m Done! {} image{} saved to \x1b[1m{}\x1b[0m",
+        saved,
+        if saved == 1 { "" } else { "s" },
+        shorten_path(&output_dir)
+    );
+
+    // Step 6: Prompt for ratings if enabled
+    if opts.rate && !all_results.is_empty() {
+        prompt_for_ratings(&all_results).await?;
+    }
+
+    Ok(finish_batch(records, output_dir, opts, quiet))
+}
+
+/// What a run of `cmd_find` leaves behind, for the exit status and `--report`
+#[derive(Debug, Default)]
+struct Run {
+    /// Not set with `--stdout`, or when cancelled before searching
+    output_dir: Option<PathBuf>,
+    records: Vec<output::QueryRecord>,
+    /// The user declined at a confirmation prompt
+    cancelled: bool,
+}
+
+impl Run {
+    fn status(&self, fail_on: output::FailOn) -> output::ExitStatus {
+        if self.cancelled {
+            return output::ExitStatus::Cancelled;
+        }
+        let failed = self.records.iter().filter(|r| r.failed()).count();
+        fail_on.status(failed, self.records.len())
+    }
+}
+
+/// Print the queries that failed and wrap up the run
+fn finish_batch(
+    records: Vec<output::QueryRecord>,
+    output_dir: PathBuf,
+    opts: &FindOptions,
+    quiet: bool,
+) -> Run {
+    let failed: Vec<&output::QueryRecord> = records.iter().filter(|r| r.failed()).collect();
+
+    if !quiet && !failed.is_empty() {
+        println!(
+            "\n  \x1b[1m{} of {} queries succeeded.\x1b[0m Failed:",
+            records.len() - failed.len(),
+            records.len()
+        );
+        for record in &failed {
+            let reason = match &record.error {
+                Some(error) => error.to_string(),
+                None => "all downloads failed".to_string(),
+            };
+            // Hints and details go on further lines; the first says what happened
+            println!(
+                "    \x1b[31m✗\x1b[0m {} \x1b[90m— {}\x1b[0m",
+                record.query,
+                reason.lines().next().unwrap_or_default()
+            );
+        }
+    }
+
+    Run {
+        output_dir: (!opts.to_stdout).then_some(output_dir),
+        records,
+        cancelled: false,
+    }
+}
+
+/// Show every candidate for one query and let the user choose any number of them.
+///
+/// Choices are saved as implicit feedback: picked images count as thumbs up,
+/// passed-over ones as thumbs down. Skipping the query records nothing.
+fn pick_candidates(pending: &PendingPick) -> Result<Vec<DownloadedImageInfo>> {
+    if pending.candidates.is_empty() {
+        println!("  \x1b[33m!\x1b[0m No candidates for \"{}\"\n", pending.query);
+        return Ok(Vec::new());
+    }
+
+    let labels: Vec<String> = pending
+        .candidates
+        .iter()
+        .map(|c| {
+            format!(
+                "{} \x1b[90m{} · {}\x1b[0m",
+                truncate_title(&c.title, 50),
+                format_dimensions(c.width, c.height),
+                download::url_domain(&c.download_url)
+            )
+        })
+        .collect();
+
+    let chosen = dialoguer::MultiSelect::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(format!(
+            "{} \x1b[90m(↑/↓ move, space select, enter confirm, esc skip)\x1b[0m",
+            pending.query
+        ))
+        .items(&labels)
+        .report(false)
+        .interact_opt()?
+        .unwrap_or_default();
+
+    // Esc or confirming nothing skips the query; that says nothing about the candidates
+    if chosen.is_empty() {
+        println!("  \x1b[90m- {}: skipped\x1b[0m", pending.query);
+        return Ok(Vec::new());
+    }
+
+    let mut picks = Vec::new();
+    for (i, candidate) in pending.candidates.iter().enumerate() {
+        let picked = chosen.contains(&i);
+        let found = Pick::new(candidate.clone(), &pending.expanded);
+        let rating = if picked {
+            feedback::Rating::ThumbsUp
+        } else {
+            feedback::Rating::ThumbsDown
+        };
+        feedback::append_entry(found.feedback(rating, true))?;
+
+        if picked {
+            picks.push(DownloadedImageInfo::new(found));
+        }
+    }
+
+    println!(
+        "  \x1b[32m✓\x1b[0m {}: {} of {} selected",
+        pending.query,
+        picks.len(),
+        pending.candidates.len()
+    );
+
+    number_picks(&mut picks, pending.record);
+    Ok(picks)
+}
+
+/// Fetch and render thumbnails for all results concurrently
+async fn load_previews(
+    results: &mut [DownloadedImageInfo],
+    protocol: preview::Protocol,
+    limiter: &Arc<download::HostLimiter>,
+) {
+    let spinner = create_spinner("Loading previews...", false);
+    let handles: Vec<_> = results
+        .iter()
+        .map(|info| {
+            let url = info.found.image.download_url.clone();
+            let limiter = Arc::clone(limiter);
+            tokio::spawn(async move { preview::load(&limiter, &url, protocol, "      ").await })
+        })
+        .collect();
+
+    for (info, handle) in results.iter_mut().zip(handles) {
+        info.preview = Some(match handle.await {
+            Ok(Ok(rendered)) => rendered,
+            Ok(Err(e)) => format!("      \x1b[90m(no preview: {})\x1b[0m", e),
+            Err(_) => "      \x1b[90m(no preview)\x1b[0m".to_string(),
+        });
+    }
+    spinner.finish_and_clear();
+}
+
+/// Prompt user to rate downloaded images
+async fn prompt_for_ratings(results: &[DownloadedImageInfo]) -> Result<()> {
+    println!("\n  \x1b[1mRate these results to help improve future searches:\x1b[0m");
+    println!("  \x1b[90m(1 = thumbs up, 2 = thumbs down, Enter = skip)\x1b[0m\n");
+
+    for info in results {
+        if let Some(preview) = &info.preview {
+            println!("{}", preview);
+        }
+        print!(
+            "  {} \x1b[90m[1/2/Enter]\x1b[0m ",
+            truncate_title(&info.found.image.source_query, 40)
+        );
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let input = input.trim();
+
+        let rating = match input {
+            "1" => feedback::Rating::ThumbsUp,
+            "2" => feedback::Rating::ThumbsDown,
+            _ => feedback::Rating::Skip,
+        };
+
+        feedback::append_entry(info.found.feedback(rating, false))?;
+
+        let rating_str = match rating {
+            feedback::Rating::ThumbsUp => "\x1b[32m+\x1b[0m",
+            feedback::Rating::ThumbsDown => "\x1b[31m-\x1b[0m",
+            feedback::Rating::Skip => "\x1b[90m~\x1b[0m",
+        };
+        println!("    {}", rating_str);
+    }
+
+    println!("\n  \x1b[90mFeedback saved. Run 'fetchr history stats' to view.\x1b[0m");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_title_counts_characters() {
+        assert_eq!(truncate_title("Tesla logo", 10), "Tesla logo");
+        assert_eq!(truncate_title("Tesla logo png", 10), "Tesla l...");
+        assert_eq!(truncate_title("東京タワーの夜景写真", 8), "東京タワー...");
+        assert_eq!(truncate_title("Café crème brûlée", 9), "Café c...");
+    }
+}
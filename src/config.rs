@@ -1,45 +1,233 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+use crate::download::ConflictPolicy;
+
+/// Per-project config file, looked up from the working directory upwards
+const PROJECT_CONFIG_FILE: &str = ".fetchr.toml";
+/// `defaults.output_dir` as written by old versions of `save`, meaning "unset"
+const LEGACY_OUTPUT_DIR: &str = "./downloads";
+/// Overrides where config, history and cache are kept
+const HOME_ENV: &str = "FETCHR_HOME";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub keys: Keys,
     #[serde(default)]
     pub defaults: Defaults,
+    #[serde(default)]
+    pub search: Search,
+    #[serde(default)]
+    pub ai: Ai,
+    #[serde(default)]
+    pub download: Download,
+    #[serde(default)]
+    pub cache: Cache,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Keys {
     pub gemini: Option<String>,
     pub serper: Option<String>,
+    pub openai: Option<String>,
+    pub anthropic: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Defaults {
+    /// Number of search candidates fetched per query
     pub limit: usize,
-    pub output_dir: String,
+    /// Download directory; `~` and `$VARS` are expanded. Unset means system Downloads/fetchr
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    /// The effective `output_dir` as worked out by `load`: expanded, and a
+    /// project's made relative to its `.fetchr.toml`
+    #[serde(skip)]
+    pub resolved_output_dir: Option<PathBuf>,
 }
 
 impl Default for Defaults {
     fn default() -> Self {
         Self {
             limit: 5,
-            output_dir: "./downloads".to_string(),
+            output_dir: None,
+            resolved_output_dir: None,
         }
     }
 }
 
-fn config_path() -> Result<PathBuf> {
-    let config_dir = dirs::config_dir()
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Download {
+    /// Filename template, see `download::DownloadOptions`
+    pub name_template: String,
+    /// What to do when a file with the same name exists
+    pub on_conflict: ConflictPolicy,
+    /// Downloads in flight across all hosts
+    pub max_concurrent: usize,
+    /// Downloads in flight to any one host
+    pub per_host: usize,
+    /// Minimum gap between requests to the same host, in milliseconds
+    pub host_delay_ms: u64,
+    /// Retries when a host answers 429/5xx or the connection drops
+    pub max_retries: u32,
+}
+
+impl Default for Download {
+    fn default() -> Self {
+        Self {
+            name_template: "{query}".to_string(),
+            on_conflict: ConflictPolicy::Suffix,
+            max_concurrent: 8,
+            per_host: 2,
+            host_delay_ms: 250,
+            max_retries: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cache {
+    /// Reuse LLM expansions and search results from earlier runs
+    pub enabled: bool,
+    /// How long an expansion stays fresh, in hours
+    pub expand_ttl_hours: u64,
+    /// How long search results stay fresh, in hours
+    pub search_ttl_hours: u64,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            expand_ttl_hours: 24 * 7,
+            search_ttl_hours: 24,
+        }
+    }
+}
+
+/// Overrides from a project's `.fetchr.toml`. API keys are deliberately not read
+/// from here so the file can be committed.
+#[derive(Debug, Default, Deserialize)]
+struct ProjectConfig {
+    #[serde(default)]
+    defaults: ProjectDefaults,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProjectDefaults {
+    limit: Option<usize>,
+    output_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Search {
+    /// Image search backend (see `search::PROVIDERS`)
+    pub provider: String,
+    /// API base URL override, e.g. a proxy or local mock server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self {
+            provider: "serper".to_string(),
+            base_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ai {
+    /// Query expansion backend (see `ai::PROVIDERS`)
+    pub provider: String,
+    /// Model name override (each provider has its own default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// API base URL override, e.g. a proxy or local mock server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+}
+
+impl Default for Ai {
+    fn default() -> Self {
+        Self {
+            provider: "gemini".to_string(),
+            model: None,
+            base_url: None,
+        }
+    }
+}
+
+/// Where config.toml and history.json live: `$FETCHR_HOME` if set (e.g. to
+/// sandbox tests), else the platform config directory
+pub(crate) fn config_dir() -> Result<PathBuf> {
+    if let Some(home) = std::env::var_os(HOME_ENV) {
+        return Ok(PathBuf::from(home));
+    }
+    Ok(dirs::config_dir()
         .context("Could not find config directory")?
-        .join("fetchr");
-    Ok(config_dir.join("config.toml"))
+        .join("fetchr"))
+}
+
+/// Where the response cache lives: `$FETCHR_HOME/cache` if set, else the
+/// platform cache directory
+pub(crate) fn cache_dir() -> Result<PathBuf> {
+    if let Some(home) = std::env::var_os(HOME_ENV) {
+        return Ok(PathBuf::from(home).join("cache"));
+    }
+    Ok(dirs::cache_dir()
+        .context("Could not find cache directory")?
+        .join("fetchr"))
 }
 
+fn config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
+/// Find the nearest project config in the working directory or its parents
+fn project_config_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Load the effective config: user config and environment, overlaid with the project config
 pub fn load() -> Result<Config> {
+    let mut config = load_user()?;
+    config.defaults.resolved_output_dir = config.defaults.output_dir.as_deref().map(expand_path).transpose()?;
+
+    if let Some(path) = project_config_path() {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read project config from {:?}", path))?;
+        let project: ProjectConfig = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse project config {:?}", path))?;
+
+        if let Some(limit) = project.defaults.limit {
+            config.defaults.limit = limit;
+        }
+        if let Some(dir) = project.defaults.output_dir {
+            // Relative paths are relative to the project, not wherever fetchr runs from
+            let project_dir = path.parent().unwrap_or(Path::new("."));
+            config.defaults.resolved_output_dir = Some(project_dir.join(expand_path(&dir)?));
+            config.defaults.output_dir = Some(dir);
+        }
+    }
+
+    Ok(config)
+}
+
+/// Load only the user config file and environment, as written by `save`
+fn load_user() -> Result<Config> {
     let path = config_path()?;
 
     let mut config = if path.exists() {
@@ -50,6 +238,12 @@ pub fn load() -> Result<Config> {
         Config::default()
     };
 
+    // Versions before output_dir was configurable wrote this placeholder on every
+    // save; honouring it would move downloads to wherever fetchr happens to run
+    if config.defaults.output_dir.as_deref() == Some(LEGACY_OUTPUT_DIR) {
+        config.defaults.output_dir = None;
+    }
+
     // Override with environment variables if set
     if config.keys.gemini.is_none() {
         if let Ok(key) = std::env::var("GEMINI_API_KEY") {
@@ -61,11 +255,21 @@ pub fn load() -> Result<Config> {
             config.keys.serper = Some(key);
         }
     }
+    if config.keys.openai.is_none() {
+        if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+            config.keys.openai = Some(key);
+        }
+    }
+    if config.keys.anthropic.is_none() {
+        if let Ok(key) = std::env::var("ANTHROPIC_API_KEY") {
+            config.keys.anthropic = Some(key);
+        }
+    }
 
     Ok(config)
 }
 
-pub fn save(config: &Config) -> Result<()> {
+pub(crate) fn save(config: &Config) -> Result<()> {
     let path = config_path()?;
 
     if let Some(parent) = path.parent() {
@@ -91,19 +295,69 @@ pub fn save(config: &Config) -> Result<()> {
     Ok(())
 }
 
-pub fn set_key(provider: &str, key: &str) -> Result<()> {
-    let mut config = load()?;
+pub(crate) fn set_key(provider: &str, key: &str) -> Result<()> {
+    let mut config = load_user()?;
 
     match provider.to_lowercase().as_str() {
         "gemini" => config.keys.gemini = Some(key.to_string()),
         "serper" => config.keys.serper = Some(key.to_string()),
-        _ => anyhow::bail!("Unknown provider: {}. Use 'gemini' or 'serper'.", provider),
+        "openai" => config.keys.openai = Some(key.to_string()),
+        "anthropic" => config.keys.anthropic = Some(key.to_string()),
+        _ => anyhow::bail!(
+            "Unknown provider: {}. Use 'gemini', 'serper', 'openai' or 'anthropic'.",
+            provider
+        ),
     }
 
     save(&config)?;
     Ok(())
 }
 
+/// Expand a leading `~` and `$VAR` / `${VAR}` references in a path
+pub(crate) fn expand_path(input: &str) -> Result<PathBuf> {
+    let mut expanded = String::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+
+        let name: String = if chars.peek() == Some(&'{') {
+            chars.next();
+            chars.by_ref().take_while(|&c| c != '}').collect()
+        } else {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            name
+        };
+
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+
+        let value = std::env::var(&name).with_context(|| {
+            format!("Environment variable ${} is not set (in path {})", name, input)
+        })?;
+        expanded.push_str(&value);
+    }
+
+    if expanded == "~" || expanded.starts_with("~/") {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        return Ok(home.join(expanded[1..].trim_start_matches('/')));
+    }
+
+    Ok(PathBuf::from(expanded))
+}
+
 /// Replace home directory with ~ for cleaner display
 fn shorten_path(path: &std::path::Path) -> String {
     if let Some(home) = dirs::home_dir() {
@@ -114,9 +368,12 @@ fn shorten_path(path: &std::path::Path) -> String {
     path.display().to_string()
 }
 
-pub fn show() -> Result<()> {
+pub(crate) fn show() -> Result<()> {
     let path = config_path()?;
     println!("Config file: {}", shorten_path(&path));
+    if let Some(project) = project_config_path() {
+        println!("Project config: {}", shorten_path(&project));
+    }
 
     let config = load()?;
 
@@ -129,10 +386,71 @@ pub fn show() -> Result<()> {
         "serper = {}",
         config.keys.serper.as_ref().map(|_| "***").unwrap_or("(not set)")
     );
+    println!(
+        "openai = {}",
+        config.keys.openai.as_ref().map(|_| "***").unwrap_or("(not set)")
+    );
+    println!(
+        "anthropic = {}",
+        config.keys.anthropic.as_ref().map(|_| "***").unwrap_or("(not set)")
+    );
 
     println!("\n[defaults]");
     println!("limit = {}", config.defaults.limit);
-    println!("output_dir = {}", config.defaults.output_dir);
+    println!(
+        "output_dir = {}",
+        config
+            .defaults
+            .resolved_output_dir
+            .as_deref()
+            .map(shorten_path)
+            .unwrap_or_else(|| "(system Downloads/fetchr)".to_string())
+    );
+
+    println!("\n[search]");
+    println!("provider = {}", config.search.provider);
+    println!("base_url = {}", config.search.base_url.as_deref().unwrap_or("(default)"));
+
+    println!("\n[download]");
+    println!("name_template = {}", config.download.name_template);
+    println!("on_conflict = {:?}", config.download.on_conflict);
+    println!("max_concurrent = {}", config.download.max_concurrent);
+    println!("per_host = {}", config.download.per_host);
+    println!("host_delay_ms = {}", config.download.host_delay_ms);
+    println!("max_retries = {}", config.download.max_retries);
+
+    println!("\n[cache]");
+    println!("enabled = {}", config.cache.enabled);
+    println!("expand_ttl_hours = {}", config.cache.expand_ttl_hours);
+    println!("search_ttl_hours = {}", config.cache.search_ttl_hours);
+
+    println!("\n[ai]");
+    println!("provider = {}", config.ai.provider);
+    println!("model = {}", config.ai.model.as_deref().unwrap_or("(default)"));
+    println!("base_url = {}", config.ai.base_url.as_deref().unwrap_or("(default)"));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_home_and_variables() {
+        std::env::set_var("FETCHR_TEST_ASSETS", "/srv/assets");
+        let home = dirs::home_dir().unwrap();
+
+        assert_eq!(expand_path("~").unwrap(), home);
+        assert_eq!(expand_path("~/Pictures/fetchr").unwrap(), home.join("Pictures/fetchr"));
+        assert_eq!(expand_path("$FETCHR_TEST_ASSETS/logos").unwrap(), PathBuf::from("/srv/assets/logos"));
+        assert_eq!(expand_path("${FETCHR_TEST_ASSETS}-old").unwrap(), PathBuf::from("/srv/assets-old"));
+    }
+
+    #[test]
+    fn leaves_everything_else_alone() {
+        assert_eq!(expand_path("images/~user").unwrap(), PathBuf::from("images/~user"));
+        assert_eq!(expand_path("cost$/$ 5").unwrap(), PathBuf::from("cost$/$ 5"));
+        assert!(expand_path("$FETCHR_TEST_UNSET/logos").is_err());
+    }
+}
@@ -1,9 +1,18 @@
 use anyhow::{Context, Result};
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::path::{Path, PathBuf};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::fs;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, SemaphorePermit};
 
+use crate::config::{self, Config};
+use crate::errors::FetchrError;
+use crate::http::{self, RetryPolicy};
 use crate::search::ImageResult;
+use crate::sniff::{self, ImageFormat};
 
 /// Get the default download directory (system Downloads/fetchr)
 pub fn get_download_dir() -> Result<PathBuf> {
@@ -12,6 +21,205 @@ pub fn get_download_dir() -> Result<PathBuf> {
     Ok(downloads.join("fetchr"))
 }
 
+/// Resolve where to save images: `--output`, then project or user
+/// `defaults.output_dir`, then the system Downloads/fetchr folder.
+///
+/// `--output` is used as given, since the shell has already expanded it.
+pub fn resolve_output_dir(cli_output: Option<&Path>, config: &Config) -> Result<PathBuf> {
+    if let Some(dir) = cli_output {
+        return Ok(dir.to_path_buf());
+    }
+    if let Some(dir) = &config.defaults.resolved_output_dir {
+        return Ok(dir.clone());
+    }
+    // A config built in code rather than by `config::load`
+    if let Some(dir) = &config.defaults.output_dir {
+        return config::expand_path(dir);
+    }
+    get_download_dir()
+}
+
+/// `dir` inside `root`, for output directories chosen by API and MCP clients:
+/// absolute paths and `..` are refused, and nothing is expanded
+pub fn confined_dir(root: &Path, dir: &Path) -> Result<PathBuf> {
+    if !dir.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        anyhow::bail!("output_dir must be a relative path inside {}", root.display());
+    }
+    Ok(root.join(dir))
+}
+
+/// What to do when the target file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Replace the existing file
+    Overwrite,
+    /// Keep the existing file and don't save the new one
+    Skip,
+    /// Save as name-1, name-2, ...
+    Suffix,
+    /// Treat the download as failed
+    Fail,
+}
+
+/// How downloaded files are named and placed
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Filename template without extension; `/` creates subfolders.
+    /// Placeholders: {query} {n} {index} {id} {width} {height} {date} {domain} {title}.
+    /// When several images are picked per query and {n} is absent, "-{n}" is appended.
+    pub name_template: String,
+    pub on_conflict: ConflictPolicy,
+    /// Hide progress bars and the failure summary (for `--format json`)
+    pub quiet: bool,
+}
+
+/// How hard downloads may hit image hosts
+#[derive(Debug, Clone)]
+pub struct DownloadLimits {
+    /// Downloads in flight across all hosts
+    pub max_concurrent: usize,
+    /// Downloads in flight to any one host
+    pub per_host: usize,
+    /// Minimum gap between starting two requests to the same host
+    pub host_delay: Duration,
+    /// Retries after a 429/5xx from the host or a dropped connection
+    pub max_retries: u32,
+}
+
+impl DownloadLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_concurrent: config.download.max_concurrent.max(1),
+            per_host: config.download.per_host.max(1),
+            host_delay: Duration::from_millis(config.download.host_delay_ms),
+            max_retries: config.download.max_retries,
+        }
+    }
+}
+
+/// Schedules requests to image hosts within `DownloadLimits`, shared by every
+/// probe, preview and download in a run
+pub struct HostLimiter {
+    limits: DownloadLimits,
+    global: Semaphore,
+    hosts: Mutex<HashMap<String, Arc<HostSlot>>>,
+}
+
+struct HostSlot {
+    permits: Arc<Semaphore>,
+    /// Earliest time the next request to this host may start
+    next_start: Mutex<Instant>,
+}
+
+/// Held for the duration of one request
+pub(crate) struct Ticket<'a> {
+    _global: SemaphorePermit<'a>,
+    _host: OwnedSemaphorePermit,
+}
+
+impl HostLimiter {
+    pub fn new(limits: DownloadLimits) -> Self {
+        Self {
+            global: Semaphore::new(limits.max_concurrent),
+            limits,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn slot(&self, url: &str) -> Arc<HostSlot> {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts
+            .entry(url_domain(url))
+            .or_insert_with(|| {
+                Arc::new(HostSlot {
+                    permits: Arc::new(Semaphore::new(self.limits.per_host)),
+                    next_start: Mutex::new(Instant::now()),
+                })
+            })
+            .clone()
+    }
+
+    /// Wait for a free global and per-host slot, then for the host's politeness delay
+    async fn acquire(&self, url: &str) -> Ticket<'_> {
+        let slot = self.slot(url);
+        let host = slot.permits.clone().acquire_owned().await.expect("semaphore closed");
+        let global = self.global.acquire().await.expect("semaphore closed");
+
+        let start = {
+            let mut next = slot.next_start.lock().unwrap();
+            let start = (*next).max(Instant::now());
+            *next = start + self.limits.host_delay;
+            start
+        };
+        tokio::time::sleep_until(start.into()).await;
+
+        Ticket {
+            _global: global,
+            _host: host,
+        }
+    }
+
+    /// Send `request` to `url` once a ticket is free, retrying per `policy`
+    /// with the whole host held off in between. The ticket comes back with the
+    /// response so it's held while the body is read.
+    pub(crate) async fn send<F>(
+        &self,
+        url: &str,
+        policy: &RetryPolicy,
+        mut request: F,
+    ) -> Result<(reqwest::Response, Ticket<'_>), reqwest::Error>
+    where
+        F: FnMut() -> reqwest::RequestBuilder,
+    {
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let ticket = self.acquire(url).await;
+            let outcome = http::execute(request()).await;
+            match policy.next_delay(attempt, started, &outcome) {
+                Some(delay) => {
+                    self.back_off(url, delay);
+                    attempt += 1;
+                }
+                None => return outcome.map(|response| (response, ticket)),
+            }
+        }
+    }
+
+    /// Hold off every request to this host for `delay`
+    fn back_off(&self, url: &str, delay: Duration) {
+        let slot = self.slot(url);
+        let mut next = slot.next_start.lock().unwrap();
+        *next = (*next).max(Instant::now() + delay);
+    }
+}
+
+/// One image to save
+#[derive(Debug, Clone)]
+pub struct DownloadItem {
+    pub image: ImageResult,
+    /// 1-based rank among the images picked for the same query, used for {n}
+    pub pick: usize,
+    /// Number of images picked for the same query
+    pub picks: usize,
+    /// Per-query filename template, overriding `DownloadOptions::name_template`
+    pub name_template: Option<String>,
+    /// 1-based position in the whole download, used for {index}; `None` means
+    /// the position in the batch passed to `download_images`
+    pub index: Option<usize>,
+    /// Other candidates for the same query, tried in order by `Fetcher::download`
+    /// when `image` fails to download or isn't really an image
+    pub fallbacks: Vec<ImageResult>,
+}
+
+/// Where a single image ended up
+#[derive(Debug, Clone)]
+pub enum SaveOutcome {
+    Saved(PathBuf),
+    Skipped(PathBuf),
+}
+
 /// Sanitize a string to be safe for use as a filename
 fn sanitize_filename(s: &str) -> String {
     s.chars()
@@ -25,39 +233,157 @@ fn sanitize_filename(s: &str) -> String {
         .to_string()
 }
 
-pub async fn download_images(images: &[ImageResult], output_dir: &Path) -> Result<()> {
+/// Host part of a URL, without a leading "www."
+pub fn url_domain(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Fill in the naming template for one image, returning a path relative to the output dir.
+/// `position` is the item's place in its batch, used for {index} unless the item has its own.
+fn render_name(template: &str, item: &DownloadItem, position: usize) -> PathBuf {
+    let image = &item.image;
+    let index = item.index.unwrap_or(position);
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    // Keep variants of the same query apart even if the template doesn't number them
+    let template = if item.picks > 1 && !template.contains("{n}") {
+        format!("{}-{{n}}", template)
+    } else {
+        template.to_string()
+    };
+
+    let mut path = PathBuf::new();
+    for segment in template.split('/') {
+        let rendered = segment
+            .replace("{query}", &image.source_query)
+            .replace("{n}", &item.pick.to_string())
+            .replace("{index}", &index.to_string())
+            .replace("{id}", &image.id)
+            .replace("{width}", &image.width.to_string())
+            .replace("{height}", &image.height.to_string())
+            .replace("{date}", &date)
+            .replace("{domain}", &url_domain(&image.download_url))
+            .replace("{title}", &image.title);
+        let name = sanitize_filename(&rendered);
+
+        // Never let a template or query escape the output directory
+        if !name.is_empty() && name != "." && name != ".." {
+            path.push(name);
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        path.push(&image.id);
+    }
+    path
+}
+
+/// Pick the final path for a file according to the conflict policy.
+///
+/// `reserved` holds paths claimed by other downloads in the same batch, so two
+/// queries that render to the same name don't overwrite each other.
+fn claim_path(
+    output_dir: &Path,
+    stem: &Path,
+    ext: &str,
+    policy: ConflictPolicy,
+    reserved: &Mutex<HashSet<PathBuf>>,
+) -> Result<SaveOutcome> {
+    let mut reserved = reserved.lock().unwrap();
+    let candidate = output_dir.join(format!("{}.{}", stem.display(), ext));
+    let taken = |path: &Path| path.exists() || reserved.contains(path);
+
+    if !taken(&candidate) || policy == ConflictPolicy::Overwrite {
+        reserved.insert(candidate.clone());
+        return Ok(SaveOutcome::Saved(candidate));
+    }
+
+    match policy {
+        ConflictPolicy::Overwrite => unreachable!(),
+        ConflictPolicy::Skip => Ok(SaveOutcome::Skipped(candidate)),
+        ConflictPolicy::Fail => anyhow::bail!("File already exists: {}", candidate.display()),
+        ConflictPolicy::Suffix => {
+            let mut n = 1;
+            loop {
+                let path = output_dir.join(format!("{}-{}.{}", stem.display(), n, ext));
+                if !taken(&path) {
+                    reserved.insert(path.clone());
+                    return Ok(SaveOutcome::Saved(path));
+                }
+                n += 1;
+            }
+        }
+    }
+}
+
+/// State shared by every download in one `download_images` call
+struct Batch {
+    client: reqwest::Client,
+    limiter: Arc<HostLimiter>,
+    /// Paths claimed so far, see `claim_path`
+    reserved: Mutex<HashSet<PathBuf>>,
+    output_dir: PathBuf,
+    options: DownloadOptions,
+}
+
+/// Download all items concurrently. Returns one outcome per item, in order;
+/// only failing to create the output directory aborts the whole batch.
+pub async fn download_images(
+    items: &[DownloadItem],
+    output_dir: &Path,
+    options: &DownloadOptions,
+    limiter: &Arc<HostLimiter>,
+) -> Result<Vec<Result<SaveOutcome>>> {
     // Create output directory
     fs::create_dir_all(output_dir)
         .await
         .with_context(|| format!("Failed to create output directory: {}", output_dir.display()))?;
 
-    let multi_progress = MultiProgress::new();
+    let multi_progress = if options.quiet {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
     let style = ProgressStyle::default_bar()
         .template("{spinner:.green} [{bar:30.cyan/blue}] {msg}")
         .unwrap()
         .progress_chars("#>-");
 
-    let client = reqwest::Client::new();
+    let batch = Arc::new(Batch {
+        client: http::download_client().clone(),
+        limiter: Arc::clone(limiter),
+        reserved: Mutex::new(HashSet::new()),
+        output_dir: output_dir.to_path_buf(),
+        options: options.clone(),
+    });
 
     // Download all images concurrently
     let mut handles = Vec::new();
 
-    for image in images {
+    for (i, item) in items.iter().enumerate() {
+        let image = &item.image;
         let pb = multi_progress.add(ProgressBar::new(100));
         pb.set_style(style.clone());
         pb.set_message(image.id[..8.min(image.id.len())].to_string());
 
-        let client = client.clone();
-        let image = image.clone();
-        let output_dir = output_dir.to_path_buf();
+        let item = item.clone();
+        let batch = Arc::clone(&batch);
 
         let handle = tokio::spawn(async move {
-            let result = download_single(&client, &image, &output_dir, &pb).await;
-            pb.finish_with_message(format!(
-                "{} {}",
-                &image.id[..8.min(image.id.len())],
-                if result.is_ok() { "done" } else { "failed" }
-            ));
+            let result = download_single(&batch, &item, i + 1, &pb).await;
+            let status = match &result {
+                Ok(SaveOutcome::Saved(_)) => "done".to_string(),
+                Ok(SaveOutcome::Skipped(path)) => format!(
+                    "skipped ({} exists)",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                ),
+                Err(_) => "failed".to_string(),
+            };
+            let id = &item.image.id;
+            pb.finish_with_message(format!("{} {}", &id[..8.min(id.len())], status));
             result
         });
 
@@ -65,64 +391,190 @@ pub async fn download_images(images: &[ImageResult], output_dir: &Path) -> Resul
     }
 
     // Wait for all downloads
-    let mut errors = Vec::new();
+    let mut outcomes = Vec::new();
     for handle in handles {
-        if let Err(e) = handle.await? {
-            errors.push(e);
-        }
+        outcomes.push(handle.await?);
     }
 
-    if !errors.is_empty() {
+    let errors: Vec<&anyhow::Error> = outcomes.iter().filter_map(|o| o.as_ref().err()).collect();
+    if !errors.is_empty() && !options.quiet {
         eprintln!("\nSome downloads failed:");
         for e in &errors {
             eprintln!("  - {}", e);
         }
     }
 
-    Ok(())
+    Ok(outcomes)
 }
 
-async fn download_single(
+/// Fail with a user-facing hint for non-success image host responses
+fn check_status(url: &str, status: reqwest::StatusCode) -> Result<(), FetchrError> {
+    if status.is_success() {
+        return Ok(());
+    }
+    let hint = match status.as_u16() {
+        403 => "Image may be protected or hotlink-blocked",
+        404 => "Image no longer exists at this URL",
+        410 => "Image has been permanently removed",
+        429 => "Too many download requests, try again later",
+        500..=599 => "Image server is having issues",
+        _ => "Could not download image",
+    };
+    Err(FetchrError::DownloadFailed {
+        url: url.to_string(),
+        status: Some(status.as_u16()),
+        reason: hint.to_string(),
+    })
+}
+
+/// A download request that never got a response
+fn request_failed(url: &str, error: &reqwest::Error) -> FetchrError {
+    let reason = if error.is_timeout() {
+        "Image host took too long to respond"
+    } else if error.is_connect() {
+        "Could not connect to image host"
+    } else {
+        "Failed to start download"
+    };
+    download_failed(url, reason)
+}
+
+/// A download that failed before we got a usable image
+fn download_failed(url: &str, reason: impl std::fmt::Display) -> FetchrError {
+    FetchrError::DownloadFailed {
+        url: url.to_string(),
+        status: None,
+        reason: reason.to_string(),
+    }
+}
+
+fn content_type(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Probes are one of many candidates, so a slow host is simply skipped
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Fetch just the start of a URL and check that it really serves an image.
+///
+/// Used to choose between search candidates before downloading. Only the first
+/// kilobyte of a GET is read; HEAD isn't enough because many image hosts reject
+/// it, and the magic bytes catch HTML error pages served with a 200.
+pub async fn probe_image(limiter: &HostLimiter, url: &str) -> Result<ImageFormat> {
+    let probe = || http::client().get(url).timeout(PROBE_TIMEOUT);
+    let (mut response, _ticket) = limiter
+        .send(url, &RetryPolicy::PROBE, probe)
+        .await
+        .context("Failed to connect")?;
+    check_status(url, response.status())?;
+    let content_type = content_type(&response);
+
+    let mut head = Vec::new();
+    while head.len() < sniff::SNIFF_LEN {
+        match response.chunk().await.context("Failed to read image data")? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+
+    sniff::detect(content_type.as_deref(), &head)
+}
+
+/// Download a whole image and work out its real format.
+///
+/// The request waits its turn with `limiter`. Failures the retry policy deems
+/// transient pause the whole host for its `Retry-After` (or a backoff) and are retried.
+pub async fn fetch_image(
     client: &reqwest::Client,
-    image: &ImageResult,
-    output_dir: &Path,
-    pb: &ProgressBar,
-) -> Result<()> {
-    let response = client
-        .get(&image.download_url)
-        .send()
+    limiter: &HostLimiter,
+    url: &str,
+) -> Result<(Vec<u8>, ImageFormat)> {
+    let policy = RetryPolicy::download(limiter.limits.max_retries);
+    let (response, _ticket) = limiter
+        .send(url, &policy, || client.get(url))
+        .await
+        .map_err(|e| request_failed(url, &e))?;
+
+    check_status(url, response.status())?;
+    let content_type = content_type(&response);
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| download_failed(url, format!("Failed to read image data: {}", e)))?;
+    let format = sniff::detect(content_type.as_deref(), &bytes).map_err(|e| download_failed(url, e))?;
+
+    Ok((bytes.to_vec(), format))
+}
+
+/// Stream an image to `out` as it downloads, after checking from the first
+/// bytes that it really is one
+pub async fn stream_image<W: std::io::Write>(
+    url: &str,
+    policy: &RetryPolicy,
+    out: &mut W,
+) -> Result<ImageFormat> {
+    let mut response = http::send("Download", policy, || http::download_client().get(url))
         .await
         .context("Failed to start download")?;
+    check_status(url, response.status())?;
+    let content_type = content_type(&response);
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let hint = match status.as_u16() {
-            403 => "Image may be protected or hotlink-blocked",
-            404 => "Image no longer exists at this URL",
-            410 => "Image has been permanently removed",
-            429 => "Too many download requests, try again later",
-            500..=599 => "Image server is having issues",
-            _ => "Could not download image",
-        };
-        anyhow::bail!("⚠️ Download failed (HTTP {}): {}", status.as_u16(), hint);
+    let mut head = Vec::new();
+    let mut done = false;
+    while head.len() < sniff::SNIFF_LEN {
+        match response.chunk().await.context("Failed to read image data")? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => {
+                done = true;
+                break;
+            }
+        }
     }
+    let format = sniff::detect(content_type.as_deref(), &head).map_err(|e| download_failed(url, e))?;
 
-    let bytes = response.bytes().await.context("Failed to read image data")?;
+    out.write_all(&head).context("Failed to write image data")?;
+    while !done {
+        match response.chunk().await.context("Failed to read image data")? {
+            Some(chunk) => out.write_all(&chunk).context("Failed to write image data")?,
+            None => done = true,
+        }
+    }
+    out.flush().context("Failed to write image data")?;
+
+    Ok(format)
+}
+
+async fn download_single(
+    batch: &Batch,
+    item: &DownloadItem,
+    position: usize,
+    pb: &ProgressBar,
+) -> Result<SaveOutcome> {
+    let options = &batch.options;
+    let (bytes, format) = fetch_image(&batch.client, &batch.limiter, &item.image.download_url).await?;
 
     pb.set_position(50);
 
-    // Get extension from URL or default to jpg
-    let ext = image
-        .download_url
-        .rsplit('.')
-        .next()
-        .filter(|e| ["jpg", "jpeg", "png", "gif", "webp", "svg"].contains(&e.to_lowercase().as_str()))
-        .unwrap_or("jpg");
+    // Name the file after what the bytes actually are, not what the URL claims
+    let ext = format.extension();
+
+    let template = item.name_template.as_deref().unwrap_or(&options.name_template);
+    let stem = render_name(template, item, position);
+    let filepath = match claim_path(&batch.output_dir, &stem, ext, options.on_conflict, &batch.reserved)? {
+        SaveOutcome::Saved(path) => path,
+        skipped => return Ok(skipped),
+    };
 
-    // Use sanitized source query as filename
-    let base_name = sanitize_filename(&image.source_query);
-    let filename = format!("{}.{}", base_name, ext);
-    let filepath = output_dir.join(&filename);
+    if let Some(parent) = filepath.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
 
     fs::write(&filepath, &bytes)
         .await
@@ -130,5 +582,107 @@ async fn download_single(
 
     pb.set_position(100);
 
-    Ok(())
+    Ok(SaveOutcome::Saved(filepath))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(query: &str, pick: usize, picks: usize) -> DownloadItem {
+        DownloadItem {
+            image: ImageResult {
+                id: "a1b2c3d4".to_string(),
+                title: "Tesla: Logo?".to_string(),
+                download_url: "https://www.example.com/logo.png".to_string(),
+                width: 640,
+                height: 480,
+                source_query: query.to_string(),
+            },
+            pick,
+            picks,
+            name_template: None,
+            index: None,
+            fallbacks: Vec::new(),
+        }
+    }
+
+    fn claim(
+        dir: &Path,
+        stem: &str,
+        policy: ConflictPolicy,
+        reserved: &Mutex<HashSet<PathBuf>>,
+    ) -> Result<SaveOutcome> {
+        claim_path(dir, Path::new(stem), "png", policy, reserved)
+    }
+
+    #[test]
+    fn renders_template_fields() {
+        let item = item("tesla logo", 1, 1);
+        assert_eq!(render_name("{query}", &item, 1), PathBuf::from("tesla logo"));
+        assert_eq!(
+            render_name("{domain}/{id}-{width}x{height}-{index}", &item, 3),
+            PathBuf::from("example.com/a1b2c3d4-640x480-3")
+        );
+        assert_eq!(render_name("{title}", &item, 1), PathBuf::from("Tesla_ Logo_"));
+    }
+
+    #[test]
+    fn numbers_several_picks_even_without_n() {
+        assert_eq!(render_name("{query}", &item("cat", 2, 3), 1), PathBuf::from("cat-2"));
+        assert_eq!(render_name("{n}_{query}", &item("cat", 2, 3), 1), PathBuf::from("2_cat"));
+    }
+
+    #[test]
+    fn index_comes_from_the_item_when_set() {
+        // A fallback retried in a batch of its own keeps its place in the whole download
+        let first = item("cat", 1, 1);
+        let mut retried = item("dog", 1, 1);
+        retried.index = Some(2);
+        assert_eq!(render_name("image-{index}", &first, 1), PathBuf::from("image-1"));
+        assert_eq!(render_name("image-{index}", &retried, 1), PathBuf::from("image-2"));
+    }
+
+    #[test]
+    fn names_stay_inside_the_output_dir() {
+        assert_eq!(render_name("{query}", &item("../../etc/passwd", 1, 1), 1), PathBuf::from(".._.._etc_passwd"));
+        assert_eq!(render_name("../{query}/./x", &item("cat", 1, 1), 1), PathBuf::from("cat/x"));
+        assert_eq!(render_name("{query}", &item("  ", 1, 1), 1), PathBuf::from("a1b2c3d4"));
+    }
+
+    #[test]
+    fn conflict_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("cat.png"), b"existing").unwrap();
+        let reserved = Mutex::new(HashSet::new());
+
+        let outcome = claim(dir.path(), "cat", ConflictPolicy::Overwrite, &reserved).unwrap();
+        assert!(matches!(outcome, SaveOutcome::Saved(path) if path == dir.path().join("cat.png")));
+        let outcome = claim(dir.path(), "cat", ConflictPolicy::Skip, &reserved).unwrap();
+        assert!(matches!(outcome, SaveOutcome::Skipped(path) if path == dir.path().join("cat.png")));
+        assert!(claim(dir.path(), "cat", ConflictPolicy::Fail, &reserved).is_err());
+        let outcome = claim(dir.path(), "cat", ConflictPolicy::Suffix, &reserved).unwrap();
+        assert!(matches!(outcome, SaveOutcome::Saved(path) if path == dir.path().join("cat-1.png")));
+    }
+
+    #[test]
+    fn names_claimed_in_the_same_batch_count_as_taken() {
+        let dir = tempfile::tempdir().unwrap();
+        let reserved = Mutex::new(HashSet::new());
+
+        let first = claim(dir.path(), "image-1", ConflictPolicy::Suffix, &reserved).unwrap();
+        let second = claim(dir.path(), "image-1", ConflictPolicy::Suffix, &reserved).unwrap();
+        assert!(matches!(first, SaveOutcome::Saved(path) if path == dir.path().join("image-1.png")));
+        assert!(matches!(second, SaveOutcome::Saved(path) if path == dir.path().join("image-1-1.png")));
+        assert!(claim(dir.path(), "image-1", ConflictPolicy::Fail, &reserved).is_err());
+    }
+
+    #[test]
+    fn confined_dir_refuses_escapes() {
+        let root = Path::new("/srv/fetchr");
+        assert_eq!(confined_dir(root, Path::new("logos/./2024")).unwrap(), root.join("logos/./2024"));
+        assert!(confined_dir(root, Path::new("../escape")).is_err());
+        assert!(confined_dir(root, Path::new("logos/../../escape")).is_err());
+        assert!(confined_dir(root, Path::new("/tmp")).is_err());
+    }
 }
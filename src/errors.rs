@@ -1,15 +1,127 @@
+use std::fmt;
+
 use reqwest::StatusCode;
+use serde::Serialize;
+
+/// Everything that can go wrong talking to a service or fetching an image,
+/// classified so callers can decide whether to skip, stop or retry.
+///
+/// `Display` renders the familiar icon + hint message; the fields are kept so
+/// callers and `--format json` can inspect what went wrong.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FetchrError {
+    /// Missing, invalid or revoked API key
+    Auth(ServiceError),
+    /// Credits or billing quota used up
+    Quota(ServiceError),
+    /// Still throttled after backing off
+    RateLimited(ServiceError),
+    /// 5xx from the service
+    ServiceDown(ServiceError),
+    /// The service refused the request itself (400, 404, other 4xx)
+    BadRequest(ServiceError),
+    /// A reply we couldn't make sense of
+    BadResponse(ServiceError),
+    /// No reply at all: timeouts, refused or dropped connections
+    Network(ServiceError),
+    /// The search came back empty
+    NoResults { query: String },
+    /// Missing API key or an unreadable config file
+    Config { message: String },
+    /// An image URL didn't serve a usable image
+    DownloadFailed {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+        reason: String,
+    },
+}
+
+/// A failed call to an external service, with a user-facing explanation
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceError {
+    pub service: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub message: String,
+    pub hint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip)]
+    icon: &'static str,
+}
+
+impl FetchrError {
+    /// The service error behind this failure, if it came from an API call
+    pub fn service_error(&self) -> Option<&ServiceError> {
+        match self {
+            FetchrError::Auth(e)
+            | FetchrError::Quota(e)
+            | FetchrError::RateLimited(e)
+            | FetchrError::ServiceDown(e)
+            | FetchrError::BadRequest(e)
+            | FetchrError::BadResponse(e)
+            | FetchrError::Network(e) => Some(e),
+            FetchrError::Config { .. }
+            | FetchrError::NoResults { .. }
+            | FetchrError::DownloadFailed { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for FetchrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchrError::Config { message } => write!(f, "{}", message),
+            FetchrError::NoResults { query } => write!(f, "🔍 No results for \"{}\"", query),
+            FetchrError::DownloadFailed {
+                status: Some(status),
+                reason,
+                ..
+            } => write!(f, "⚠️ Download failed (HTTP {}): {}", status, reason),
+            FetchrError::DownloadFailed { reason, .. } => write!(f, "⚠️ Download failed: {}", reason),
+            other => {
+                let e = other.service_error().expect("service error variant");
+                if e.status.is_some() {
+                    write!(f, "{} {} error: {}", e.icon, e.service, e.message)?;
+                } else {
+                    write!(f, "{} {}: {}", e.icon, e.service, e.message)?;
+                }
+
+                // Add hint
+                write!(f, "\n   Hint: {}", e.hint)?;
+
+                if let Some(detail) = &e.detail {
+                    write!(f, "\n   Detail: {}", detail)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for FetchrError {}
+
+/// Build an error for a non-success API response
+pub fn api_error(service: &str, status: StatusCode, body: &str) -> FetchrError {
+    let (kind, icon, explanation, hint): (Kind, _, _, _) = match status {
+        _ if is_quota_error(status, body) => (
+            FetchrError::Quota,
+            "💳",
+            "Quota exhausted",
+            "Your API credits or quota are used up. Check your billing.".to_string(),
+        ),
 
-/// Format an API error into a user-friendly message
-pub fn format_api_error(service: &str, status: StatusCode, body: &str) -> String {
-    let (icon, explanation, hint) = match status {
         // Authentication errors
         StatusCode::UNAUTHORIZED => (
+            FetchrError::Auth,
             "🔑",
             "Invalid API key",
             format!("Run: fetchr config set-key {} <YOUR_KEY>", service.to_lowercase()),
         ),
         StatusCode::FORBIDDEN => (
+            FetchrError::Auth,
             "🚫",
             "Access denied",
             "Your API key may lack permissions or be revoked. Check your API dashboard.".to_string(),
@@ -17,6 +129,7 @@ pub fn format_api_error(service: &str, status: StatusCode, body: &str) -> String
 
         // Rate limiting
         StatusCode::TOO_MANY_REQUESTS => (
+            FetchrError::RateLimited,
             "⏳",
             "Rate limit exceeded",
             "Too many requests. Wait a moment and try again.".to_string(),
@@ -24,11 +137,13 @@ pub fn format_api_error(service: &str, status: StatusCode, body: &str) -> String
 
         // Server errors
         StatusCode::SERVICE_UNAVAILABLE | StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => (
+            FetchrError::ServiceDown,
             "🔧",
             "Service temporarily unavailable",
             format!("{} is experiencing issues. Try again in a few minutes.", service),
         ),
         StatusCode::INTERNAL_SERVER_ERROR => (
+            FetchrError::ServiceDown,
             "💥",
             "Server error",
             format!("{} encountered an internal error. This is not your fault.", service),
@@ -36,11 +151,13 @@ pub fn format_api_error(service: &str, status: StatusCode, body: &str) -> String
 
         // Client errors
         StatusCode::BAD_REQUEST => (
+            FetchrError::BadRequest,
             "❌",
             "Invalid request",
             "The search query may contain invalid characters.".to_string(),
         ),
         StatusCode::NOT_FOUND => (
+            FetchrError::BadRequest,
             "🔍",
             "Not found",
             "The API endpoint may have changed. Check for updates.".to_string(),
@@ -48,34 +165,60 @@ pub fn format_api_error(service: &str, status: StatusCode, body: &str) -> String
 
         // Payment/quota
         StatusCode::PAYMENT_REQUIRED => (
+            FetchrError::Quota,
             "💳",
             "Payment required",
             "Your API quota may be exhausted. Check your billing.".to_string(),
         ),
 
         // Default
+        _ if status.is_server_error() => (
+            FetchrError::ServiceDown,
+            "💥",
+            "Server error",
+            format!("{} returned HTTP {}. Try again later.", service, status.as_u16()),
+        ),
         _ => (
+            FetchrError::BadRequest,
             "⚠️",
             "Request failed",
             format!("HTTP {} - check your internet connection", status.as_u16()),
         ),
     };
 
-    // Build the message
-    let mut msg = format!("{} {} error: {}", icon, service, explanation);
+    // Keep technical details only if the body is short enough to be useful
+    let detail = if !body.is_empty() && body.len() < 200 {
+        extract_error_message(body)
+    } else {
+        None
+    };
+
+    kind(ServiceError {
+        service: service.to_string(),
+        status: Some(status.as_u16()),
+        message: explanation.to_string(),
+        hint,
+        detail,
+        icon,
+    })
+}
 
-    // Add hint
-    msg.push_str(&format!("\n   Hint: {}", hint));
+/// Constructor for one of the service-error variants of `FetchrError`
+type Kind = fn(ServiceError) -> FetchrError;
 
-    // Add technical details in debug mode (if body is useful)
-    if !body.is_empty() && body.len() < 200 {
-        // Try to extract a useful message from JSON error responses
-        if let Some(extracted) = extract_error_message(body) {
-            msg.push_str(&format!("\n   Detail: {}", extracted));
-        }
+/// Exhausted credits, as opposed to throttling, reported in the body of a 400
+/// or 403 (402 has its own arm in `api_error`). A 429 is a rate limit even when it
+/// mentions quota (Gemini's per-minute `RESOURCE_EXHAUSTED`) or billing
+/// (OpenAI's links), except for OpenAI's explicit `insufficient_quota`.
+fn is_quota_error(status: StatusCode, body: &str) -> bool {
+    let body = body.to_lowercase();
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::FORBIDDEN => ["quota", "credits", "billing"]
+            .iter()
+            .any(|word| body.contains(word)),
+        StatusCode::TOO_MANY_REQUESTS => body.contains("insufficient_quota"),
+        _ => false,
     }
-
-    msg
 }
 
 /// Try to extract a meaningful error message from JSON response body
@@ -98,33 +241,63 @@ fn extract_error_message(body: &str) -> Option<String> {
     None
 }
 
-/// Format a network/connection error
-pub fn format_network_error(service: &str, error: &reqwest::Error) -> String {
-    let (icon, explanation, hint) = if error.is_timeout() {
+/// Build an error for a request that never got a response
+pub fn network_error(service: &str, error: &reqwest::Error) -> FetchrError {
+    let (kind, icon, explanation, hint): (Kind, _, _, _) = if error.is_timeout() {
         (
+            FetchrError::Network,
             "⏱️",
             "Connection timed out",
             format!("{} took too long to respond. Check your internet or try again.", service),
         )
     } else if error.is_connect() {
         (
+            FetchrError::Network,
             "🌐",
             "Connection failed",
             "Check your internet connection.".to_string(),
         )
     } else if error.is_decode() {
         (
+            FetchrError::BadResponse,
             "📦",
             "Invalid response",
             format!("{} returned unexpected data. The API may have changed.", service),
         )
     } else {
         (
+            FetchrError::Network,
             "❌",
             "Network error",
             "An unexpected network error occurred.".to_string(),
         )
     };
 
-    format!("{} {}: {}\n   Hint: {}", icon, service, explanation, hint)
+    kind(ServiceError {
+        service: service.to_string(),
+        status: None,
+        message: explanation.to_string(),
+        hint,
+        detail: None,
+        icon,
+    })
+}
+
+/// Build an error for a reply that arrived but couldn't be decoded
+pub fn bad_response(service: &str, error: impl fmt::Display) -> FetchrError {
+    FetchrError::BadResponse(ServiceError {
+        service: service.to_string(),
+        status: None,
+        message: "Invalid response".to_string(),
+        hint: format!("{} returned unexpected data. The API may have changed.", service),
+        detail: Some(error.to_string()),
+        icon: "📦",
+    })
+}
+
+/// Mark a failure to load settings or build providers as a config problem
+pub fn config_error(error: anyhow::Error) -> FetchrError {
+    FetchrError::Config {
+        message: format!("{:#}", error),
+    }
 }
@@ -3,15 +3,51 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::config;
 
 /// Rating for a downloaded image
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Rating {
+    #[serde(alias = "thumbs_up")]
     ThumbsUp,
+    #[serde(alias = "thumbs_down")]
     ThumbsDown,
+    #[serde(alias = "skip")]
     Skip,
 }
 
+/// A rating as the HTTP and MCP APIs spell it. `Rating` keeps its variant
+/// names in history.json, which older versions of fetchr also read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ApiRating {
+    ThumbsUp,
+    ThumbsDown,
+    Skip,
+}
+
+impl From<ApiRating> for Rating {
+    fn from(rating: ApiRating) -> Self {
+        match rating {
+            ApiRating::ThumbsUp => Rating::ThumbsUp,
+            ApiRating::ThumbsDown => Rating::ThumbsDown,
+            ApiRating::Skip => Rating::Skip,
+        }
+    }
+}
+
+impl From<Rating> for ApiRating {
+    fn from(rating: Rating) -> Self {
+        match rating {
+            Rating::ThumbsUp => ApiRating::ThumbsUp,
+            Rating::ThumbsDown => ApiRating::ThumbsDown,
+            Rating::Skip => ApiRating::Skip,
+        }
+    }
+}
+
 /// Filters used during search
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchFilters {
@@ -29,6 +65,37 @@ pub struct FeedbackEntry {
     pub image_url: String,
     pub image_title: String,
     pub rating: Rating,
+    /// Inferred from what the user picked rather than an explicit rating
+    #[serde(default)]
+    pub implicit: bool,
+}
+
+/// A `FeedbackEntry` as the HTTP and MCP APIs return it
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ApiFeedbackEntry {
+    pub timestamp: DateTime<Utc>,
+    pub original_query: String,
+    pub expanded_query: String,
+    pub filters: SearchFilters,
+    pub image_url: String,
+    pub image_title: String,
+    pub rating: ApiRating,
+    pub implicit: bool,
+}
+
+impl From<FeedbackEntry> for ApiFeedbackEntry {
+    fn from(entry: FeedbackEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            original_query: entry.original_query,
+            expanded_query: entry.expanded_query,
+            filters: entry.filters,
+            image_url: entry.image_url,
+            image_title: entry.image_title,
+            rating: entry.rating.into(),
+            implicit: entry.implicit,
+        }
+    }
 }
 
 /// Container for all feedback history
@@ -51,14 +118,24 @@ impl FeedbackHistory {
 
 /// Get the path to the history JSON file
 fn history_path() -> Result<PathBuf> {
-    let config_dir = dirs::config_dir()
-        .context("Could not find config directory")?
-        .join("fetchr");
-    Ok(config_dir.join("history.json"))
+    Ok(config::config_dir()?.join("history.json"))
+}
+
+/// Held across every read and write of the history file. Appending is a
+/// read-modify-write, and the API and MCP servers rate from several tasks at once.
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock_history() -> MutexGuard<'static, ()> {
+    HISTORY_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 /// Load feedback history from disk
 pub fn load_history() -> Result<FeedbackHistory> {
+    let _lock = lock_history();
+    read_history()
+}
+
+fn read_history() -> Result<FeedbackHistory> {
     let path = history_path()?;
 
     if path.exists() {
@@ -70,8 +147,7 @@ pub fn load_history() -> Result<FeedbackHistory> {
     }
 }
 
-/// Save feedback history to disk
-pub fn save_history(history: &FeedbackHistory) -> Result<()> {
+fn write_history(history: &FeedbackHistory) -> Result<()> {
     let path = history_path()?;
 
     if let Some(parent) = path.parent() {
@@ -90,35 +166,47 @@ pub fn save_history(history: &FeedbackHistory) -> Result<()> {
 
 /// Append a single feedback entry to history
 pub fn append_entry(entry: FeedbackEntry) -> Result<()> {
-    let mut history = load_history()?;
+    let _lock = lock_history();
+    let mut history = read_history()?;
     history.entries.push(entry);
-    save_history(&history)?;
-    Ok(())
+    write_history(&history)
 }
 
 /// Generate learning context for the AI prompt based on past feedback
 /// Returns a formatted string with good and bad examples
-pub fn get_learning_context(limit: usize) -> Result<Option<String>> {
+pub(crate) fn get_learning_context(limit: usize) -> Result<Option<String>> {
     let history = load_history()?;
 
     if history.entries.is_empty() {
         return Ok(None);
     }
 
-    // Collect good and bad examples (most recent first)
+    // Collect good and bad examples (most recent first, one per expanded query)
+    let mut seen = std::collections::HashSet::new();
     let good_examples: Vec<&FeedbackEntry> = history
         .entries
         .iter()
         .rev()
         .filter(|e| e.rating == Rating::ThumbsUp)
+        .filter(|e| seen.insert(e.expanded_query.as_str()))
         .take(limit)
         .collect();
 
+    // A candidate passed over in the picker says nothing about the query if a
+    // sibling result from the same query was picked
+    let liked_query = |q: &str| {
+        history
+            .entries
+            .iter()
+            .any(|e| e.rating == Rating::ThumbsUp && e.expanded_query == q)
+    };
     let bad_examples: Vec<&FeedbackEntry> = history
         .entries
         .iter()
         .rev()
         .filter(|e| e.rating == Rating::ThumbsDown)
+        .filter(|e| !(e.implicit && liked_query(&e.expanded_query)))
+        .filter(|e| seen.insert(e.expanded_query.as_str()))
         .take(limit)
         .collect();
 
@@ -158,7 +246,7 @@ pub fn get_learning_context(limit: usize) -> Result<Option<String>> {
 }
 
 /// Get statistics about feedback history
-pub fn get_stats() -> Result<(usize, usize, usize)> {
+pub(crate) fn get_stats() -> Result<(usize, usize, usize)> {
     let history = load_history()?;
 
     let thumbs_up = history.entries.iter().filter(|e| e.rating == Rating::ThumbsUp).count();
@@ -169,7 +257,8 @@ pub fn get_stats() -> Result<(usize, usize, usize)> {
 }
 
 /// Clear all feedback history
-pub fn clear_history() -> Result<()> {
+pub(crate) fn clear_history() -> Result<()> {
+    let _lock = lock_history();
     let path = history_path()?;
     if path.exists() {
         fs::remove_file(&path)
@@ -0,0 +1,751 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{self, ExpandedQuery, QueryExpander};
+use crate::auto::{self, AutoDecision, AutoSession};
+use crate::cache::{self, Cache, CacheMode};
+use crate::config::{self, Config};
+use crate::download::{self, ConflictPolicy, DownloadItem, DownloadLimits, HostLimiter, SaveOutcome};
+use crate::errors::{self, FetchrError};
+use crate::feedback::{FeedbackEntry, Rating, SearchFilters};
+use crate::input::QuerySpec;
+use crate::output::{ChosenImage, ErrorRecord, ImageStatus, QueryRecord};
+use crate::search::{self, ImageResult, ImageSearchProvider};
+
+/// Pause before re-running a query whose service looked temporarily unavailable
+const QUERY_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// Extra attempts per query on top of the HTTP layer's own retries
+const QUERY_RETRIES: u32 = 1;
+
+/// Called with every step of every query, e.g. to drive spinners
+pub type ProgressFn = dyn Fn(&Progress) + Send + Sync;
+
+/// A step in finding images for one query
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    /// Position of the query in the batch
+    pub index: usize,
+    /// Queries in the batch
+    pub total: usize,
+    pub query: String,
+    pub event: Event,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Work on the query has begun
+    Started { wanted: usize },
+    /// Not run, because an earlier query hit an error every query would hit
+    Skipped,
+    /// The query was expanded and is about to be searched (not sent in auto mode,
+    /// which expands once per attempt)
+    Expanded(ExpandedQuery),
+    /// The search service looked unavailable; trying again after a pause
+    Retrying { service: &'static str },
+    /// Probing search results for ones that serve real images
+    Checking { candidates: usize },
+    /// With `candidates_only`: the search is done and the caller chooses
+    Candidates { count: usize },
+    /// Found `found` of the `wanted` images; `title` is the best one's
+    Found { title: String, found: usize, wanted: usize },
+    NoResults,
+    Failed { error: ErrorRecord },
+}
+
+/// An image chosen for a query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pick {
+    #[serde(flatten)]
+    pub image: ImageResult,
+    /// The query that found it; in auto mode each reformulation has its own
+    pub expanded: ExpandedQuery,
+}
+
+impl Pick {
+    pub fn new(image: ImageResult, expanded: &ExpandedQuery) -> Self {
+        Self {
+            image,
+            expanded: expanded.clone(),
+        }
+    }
+
+    /// A feedback entry rating this image, for `feedback::append_entry`
+    pub fn feedback(&self, rating: Rating, implicit: bool) -> FeedbackEntry {
+        FeedbackEntry {
+            timestamp: Utc::now(),
+            original_query: self.image.source_query.clone(),
+            expanded_query: self.expanded.query.clone(),
+            filters: SearchFilters {
+                img_size: self.expanded.img_size.clone(),
+                img_type: self.expanded.img_type.clone(),
+            },
+            image_url: self.image.download_url.clone(),
+            image_title: self.image.title.clone(),
+            rating,
+            implicit,
+        }
+    }
+}
+
+/// What `Fetcher::find` turned up for one query
+#[derive(Debug, Clone, Serialize)]
+pub struct FindResult {
+    pub query: String,
+    /// The search that was run; in auto mode, the last successful reformulation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expanded: Option<ExpandedQuery>,
+    /// Every search result that met the query's constraints; fallbacks come from here
+    pub candidates: Vec<ImageResult>,
+    /// Images that serve real image data, best first; empty with `candidates_only`
+    pub picks: Vec<Pick>,
+    /// Per-query filename template, from the query's spec
+    #[serde(skip)]
+    pub filename: Option<String>,
+    /// Set by `find_many` for queries that failed or found nothing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorRecord>,
+    /// Attempts, reformulations and retries, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub decisions: Vec<AutoDecision>,
+}
+
+impl FindResult {
+    fn new(spec: &QuerySpec) -> Self {
+        Self {
+            query: spec.query.clone(),
+            expanded: None,
+            candidates: Vec::new(),
+            picks: Vec::new(),
+            filename: spec.filename.clone(),
+            error: None,
+            decisions: Vec::new(),
+        }
+    }
+
+    /// The picks as numbered download items, with the other candidates as fallbacks
+    pub fn download_items(&self) -> Vec<DownloadItem> {
+        let fallbacks: Vec<ImageResult> = self
+            .candidates
+            .iter()
+            .filter(|c| !self.picks.iter().any(|p| p.image.download_url == c.download_url))
+            .cloned()
+            .collect();
+        self.picks
+            .iter()
+            .enumerate()
+            .map(|(i, pick)| DownloadItem {
+                image: pick.image.clone(),
+                pick: i + 1,
+                picks: self.picks.len(),
+                name_template: self.filename.clone(),
+                fallbacks: fallbacks.clone(),
+                index: None,
+            })
+            .collect()
+    }
+
+    /// This query as a report record, with nothing downloaded yet
+    pub fn record(&self) -> QueryRecord {
+        QueryRecord {
+            query: self.query.clone(),
+            expanded: self.expanded.clone(),
+            candidates: self.candidates.clone(),
+            chosen: Vec::new(),
+            error: self.error.clone(),
+            decisions: self.decisions.clone(),
+        }
+    }
+}
+
+/// What to do with a query whose search failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorAction {
+    /// Record the error against this query and carry on with the rest
+    Skip,
+    /// Skip every query not yet started, since they would fail the same way
+    Stop,
+    /// Run the query again after a pause
+    Retry,
+}
+
+fn error_action(error: &anyhow::Error) -> ErrorAction {
+    match error.downcast_ref::<FetchrError>() {
+        Some(FetchrError::Auth(_) | FetchrError::Quota(_)) => ErrorAction::Stop,
+        Some(FetchrError::RateLimited(_) | FetchrError::ServiceDown(_) | FetchrError::Network(_)) => {
+            ErrorAction::Retry
+        }
+        _ => ErrorAction::Skip,
+    }
+}
+
+fn log_retry(session: &mut AutoSession, query: &str, error: &anyhow::Error) {
+    let reason = error.to_string();
+    session.log(
+        query,
+        "retry",
+        &format!(
+            "{} (waiting {}s)",
+            reason.lines().next().unwrap_or_default(),
+            QUERY_RETRY_DELAY.as_secs()
+        ),
+    );
+}
+
+/// Finds and downloads images: query expansion, search, availability checks
+/// and saving, with the settings of one config file.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let fetcher = fetchr::Fetcher::builder().output_dir("assets").build()?;
+/// let found = fetcher.find("Tesla logo").await?;
+/// for image in fetcher.download(&found.download_items()).await? {
+///     println!("{:?} {:?}", image.status, image.path);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Fetcher {
+    config: Config,
+    expander: Box<dyn QueryExpander>,
+    provider: Box<dyn ImageSearchProvider>,
+    output_dir: Option<PathBuf>,
+    name_template: String,
+    on_conflict: ConflictPolicy,
+    count: usize,
+    auto: bool,
+    max_retries: u32,
+    candidates_only: bool,
+    jobs: usize,
+    progress_bars: bool,
+    on_progress: Option<Arc<ProgressFn>>,
+    /// Keeps probes and downloads within the `[download]` host limits
+    limiter: Arc<HostLimiter>,
+}
+
+/// Settings for a `Fetcher`; anything not set comes from the config file
+#[derive(Default)]
+pub struct FetcherBuilder {
+    config: Option<Config>,
+    expander: Option<Box<dyn QueryExpander>>,
+    provider: Option<Box<dyn ImageSearchProvider>>,
+    provider_name: Option<String>,
+    no_ai: bool,
+    cache: CacheMode,
+    output_dir: Option<PathBuf>,
+    name_template: Option<String>,
+    on_conflict: Option<ConflictPolicy>,
+    count: Option<usize>,
+    auto: bool,
+    max_retries: Option<u32>,
+    candidates_only: bool,
+    jobs: Option<usize>,
+    progress_bars: bool,
+    on_progress: Option<Arc<ProgressFn>>,
+}
+
+impl FetcherBuilder {
+    /// Use this config instead of loading the user and project config files
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Expand queries with this backend instead of `ai.provider`
+    pub fn expander(mut self, expander: Box<dyn QueryExpander>) -> Self {
+        self.expander = Some(expander);
+        self
+    }
+
+    /// Search with this backend instead of `search.provider`
+    pub fn provider(mut self, provider: Box<dyn ImageSearchProvider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Search with the named provider (see `search::PROVIDERS`)
+    pub fn provider_name(mut self, name: impl Into<String>) -> Self {
+        self.provider_name = Some(name.into());
+        self
+    }
+
+    /// Expand queries with the built-in offline rules instead of an LLM
+    pub fn no_ai(mut self, no_ai: bool) -> Self {
+        self.no_ai = no_ai;
+        self
+    }
+
+    pub fn cache(mut self, mode: CacheMode) -> Self {
+        self.cache = mode;
+        self
+    }
+
+    /// Where images are saved (default: `defaults.output_dir`, then Downloads/fetchr)
+    pub fn output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(dir.into());
+        self
+    }
+
+    /// Filename template (default: `download.name_template`)
+    pub fn name_template(mut self, template: impl Into<String>) -> Self {
+        self.name_template = Some(template.into());
+        self
+    }
+
+    /// What to do when a file already exists (default: `download.on_conflict`)
+    pub fn on_conflict(mut self, policy: ConflictPolicy) -> Self {
+        self.on_conflict = Some(policy);
+        self
+    }
+
+    /// Images wanted per query, unless the query's spec says otherwise (default 1)
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Reformulate the query and search again when nothing usable turns up
+    pub fn auto(mut self, auto: bool) -> Self {
+        self.auto = auto;
+        self
+    }
+
+    /// Attempts per query in auto mode (default 3)
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Return every search result without probing them, leaving the choice to the caller
+    pub fn candidates_only(mut self, candidates_only: bool) -> Self {
+        self.candidates_only = candidates_only;
+        self
+    }
+
+    /// Queries `find_many` works on at once (default 4)
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Draw download progress bars on the terminal
+    pub fn progress_bars(mut self, progress_bars: bool) -> Self {
+        self.progress_bars = progress_bars;
+        self
+    }
+
+    pub fn on_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Load the config if none was given and set up the providers. Fails with a
+    /// `FetchrError::Config` for a bad config file or an unknown provider.
+    pub fn build(self) -> Result<Fetcher> {
+        let config = match self.config {
+            Some(config) => config,
+            None => config::load().map_err(errors::config_error)?,
+        };
+        let cache = Cache::open(&config, self.cache).map_err(errors::config_error)?;
+        let expander = match self.expander {
+            Some(expander) => expander,
+            None => ai::expander_from_config(&config, self.no_ai).map_err(errors::config_error)?,
+        };
+        let provider = match self.provider {
+            Some(provider) => provider,
+            None => search::provider_from_config(&config, self.provider_name.as_deref())
+                .map_err(errors::config_error)?,
+        };
+
+        Ok(Fetcher {
+            expander: cache::expander(expander, &config, &cache),
+            provider: cache::provider(provider, &cache),
+            output_dir: self.output_dir,
+            name_template: self
+                .name_template
+                .unwrap_or_else(|| config.download.name_template.clone()),
+            on_conflict: self.on_conflict.unwrap_or(config.download.on_conflict),
+            count: self.count.unwrap_or(1).max(1),
+            auto: self.auto,
+            max_retries: self.max_retries.unwrap_or(3),
+            candidates_only: self.candidates_only,
+            jobs: self.jobs.unwrap_or(4).max(1),
+            progress_bars: self.progress_bars,
+            on_progress: self.on_progress,
+            limiter: Arc::new(HostLimiter::new(DownloadLimits::from_config(&config))),
+            config,
+        })
+    }
+}
+
+/// Sends progress for one query
+struct Reporter<'a> {
+    callback: Option<&'a ProgressFn>,
+    index: usize,
+    total: usize,
+    query: &'a str,
+}
+
+impl Reporter<'_> {
+    fn send(&self, event: Event) {
+        if let Some(callback) = self.callback {
+            callback(&Progress {
+                index: self.index,
+                total: self.total,
+                query: self.query.to_string(),
+                event,
+            });
+        }
+    }
+}
+
+impl Fetcher {
+    pub fn builder() -> FetcherBuilder {
+        FetcherBuilder::default()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The host limiter for this fetcher's requests, for the CLI's previews
+    pub(crate) fn limiter(&self) -> &Arc<HostLimiter> {
+        &self.limiter
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Where `download` saves images. Fails with a `FetchrError::Config` when no
+    /// directory is set and there's no Downloads folder to fall back to.
+    pub fn output_dir(&self) -> Result<PathBuf> {
+        download::resolve_output_dir(self.output_dir.as_deref(), &self.config)
+            .map_err(|e| errors::config_error(e).into())
+    }
+
+    /// Find images for one query; "Tesla logo @3" asks for three. An empty
+    /// `picks` means nothing usable was found.
+    pub async fn find(&self, query: &str) -> Result<FindResult> {
+        self.find_spec(&QuerySpec::parse(query)).await
+    }
+
+    /// Find images for one query with its per-query overrides
+    pub async fn find_spec(&self, spec: &QuerySpec) -> Result<FindResult> {
+        let reporter = self.reporter(0, 1, &spec.query);
+        let mut result = FindResult::new(spec);
+        let outcome = self.search(spec, &reporter, &mut result).await;
+        if let Err(e) = &outcome {
+            reporter.send(Event::Failed {
+                error: ErrorRecord::from_error(e),
+            });
+        }
+        outcome.map(|_| result)
+    }
+
+    /// Find images for every query, `jobs` at a time, in input order.
+    ///
+    /// A query that fails doesn't stop the rest: its error (or `no_results`) is
+    /// set on its result. Auth and quota errors skip the queries not yet
+    /// started, since they would fail the same way.
+    pub async fn find_many(&self, queries: &[QuerySpec]) -> Vec<FindResult> {
+        let stopped: Mutex<Option<ErrorRecord>> = Mutex::new(None);
+        // Built up front rather than with `StreamExt::map`, whose closure keeps
+        // the returned future from being provably `Send` (so it can be spawned)
+        let searches: Vec<_> = queries
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| self.find_in_batch(i, queries.len(), spec, &stopped))
+            .collect();
+        stream::iter(searches).buffered(self.jobs).collect().await
+    }
+
+    async fn find_in_batch(
+        &self,
+        index: usize,
+        total: usize,
+        spec: &QuerySpec,
+        stopped: &Mutex<Option<ErrorRecord>>,
+    ) -> FindResult {
+        let reporter = self.reporter(index, total, &spec.query);
+        let mut result = FindResult::new(spec);
+
+        if let Some(error) = stopped.lock().unwrap().clone() {
+            reporter.send(Event::Skipped);
+            result.error = Some(error);
+            return result;
+        }
+
+        match self.search(spec, &reporter, &mut result).await {
+            Ok(()) if result.picks.is_empty() && !self.candidates_only => {
+                result.error = Some(ErrorRecord::Fetchr(FetchrError::NoResults {
+                    query: spec.query.clone(),
+                }));
+            }
+            Ok(()) => {}
+            Err(e) => {
+                let error = ErrorRecord::from_error(&e);
+                if error_action(&e) == ErrorAction::Stop {
+                    stopped.lock().unwrap().get_or_insert(error.clone());
+                }
+                reporter.send(Event::Failed {
+                    error: error.clone(),
+                });
+                result.error = Some(error);
+            }
+        }
+        result
+    }
+
+    fn reporter<'a>(&'a self, index: usize, total: usize, query: &'a str) -> Reporter<'a> {
+        Reporter {
+            callback: self.on_progress.as_deref(),
+            index,
+            total,
+            query,
+        }
+    }
+
+    /// Expand, search and check availability for one query, filling in `result`
+    async fn search(&self, spec: &QuerySpec, reporter: &Reporter<'_>, result: &mut FindResult) -> Result<()> {
+        let query = &spec.query;
+        let count = spec.count.unwrap_or(self.count).max(1);
+        let constraints = spec.constraints();
+        let mut session = AutoSession::new();
+        reporter.send(Event::Started { wanted: count });
+
+        let outcome = if self.auto {
+            self.search_auto(spec, count, reporter, &mut session, result).await
+        } else {
+            self.search_once(query, count, &constraints, reporter, &mut session, result)
+                .await
+        };
+        result.decisions = session.decisions;
+        outcome?;
+
+        if self.candidates_only {
+            reporter.send(Event::Candidates {
+                count: result.candidates.len(),
+            });
+        } else if let Some(first) = result.picks.first() {
+            reporter.send(Event::Found {
+                title: first.image.title.clone(),
+                found: result.picks.len(),
+                wanted: count,
+            });
+        } else {
+            reporter.send(Event::NoResults);
+        }
+        Ok(())
+    }
+
+    /// Auto mode: reformulate and search again until enough images turn up
+    async fn search_auto(
+        &self,
+        spec: &QuerySpec,
+        count: usize,
+        reporter: &Reporter<'_>,
+        session: &mut AutoSession,
+        result: &mut FindResult,
+    ) -> Result<()> {
+        let options = auto::RetryOptions {
+            limit: self.config.defaults.limit,
+            count,
+            max_retries: self.max_retries,
+            constraints: spec.constraints(),
+        };
+
+        let mut retries = 0;
+        let found = loop {
+            match auto::find_with_retry(
+                &spec.query,
+                self.expander.as_ref(),
+                self.provider.as_ref(),
+                &self.limiter,
+                &options,
+                session,
+            )
+                .await
+            {
+                Err(e) if retries < QUERY_RETRIES && error_action(&e) == ErrorAction::Retry => {
+                    retries += 1;
+                    log_retry(session, &spec.query, &e);
+                    reporter.send(Event::Retrying {
+                        service: self.provider.name(),
+                    });
+                    tokio::time::sleep(QUERY_RETRY_DELAY).await;
+                }
+                found => break found?,
+            }
+        };
+
+        // Report the query that found the last pick, i.e. the final reformulation
+        result.expanded = found.picks.last().map(|(_, expanded)| expanded.clone());
+        result.candidates = found.candidates;
+        result.picks = found
+            .picks
+            .into_iter()
+            .map(|(image, expanded)| Pick { image, expanded })
+            .collect();
+        Ok(())
+    }
+
+    /// Normal mode: one expansion and search, keeping the first `count` distinct
+    /// candidates that serve real images
+    async fn search_once(
+        &self,
+        query: &str,
+        count: usize,
+        constraints: &search::Constraints,
+        reporter: &Reporter<'_>,
+        session: &mut AutoSession,
+        result: &mut FindResult,
+    ) -> Result<()> {
+        let mut expanded = ai::expand_prompt(query, self.expander.as_ref()).await?;
+        constraints.apply(&mut expanded);
+        result.expanded = Some(expanded.clone());
+        reporter.send(Event::Expanded(expanded.clone()));
+
+        let limit = self.config.defaults.limit.max(count);
+        let mut retries = 0;
+        let results = loop {
+            match self.provider.search_images(&expanded, query, limit).await {
+                Err(e) if retries < QUERY_RETRIES && error_action(&e) == ErrorAction::Retry => {
+                    retries += 1;
+                    log_retry(session, query, &e);
+                    reporter.send(Event::Retrying {
+                        service: self.provider.name(),
+                    });
+                    tokio::time::sleep(QUERY_RETRY_DELAY).await;
+                }
+                results => break results?,
+            }
+        };
+        let results: Vec<ImageResult> = results
+            .into_iter()
+            .filter(|r| constraints.fits(r.width, r.height))
+            .collect();
+        result.candidates = results.clone();
+
+        if self.candidates_only {
+            // Unavailable URLs surface as download failures
+            return Ok(());
+        }
+
+        reporter.send(Event::Checking {
+            candidates: results.len(),
+        });
+        for image in results {
+            if result.picks.len() >= count {
+                break;
+            }
+            if result.picks.iter().any(|p| p.image.download_url == image.download_url) {
+                continue;
+            }
+            if download::probe_image(&self.limiter, &image.download_url).await.is_ok() {
+                result.picks.push(Pick::new(image, &expanded));
+            }
+        }
+        Ok(())
+    }
+
+    /// Save images to the output directory. Returns one result per item, in
+    /// order; only failing to create the output directory is an error.
+    ///
+    /// An image that fails to download, or turns out not to be an image, is
+    /// replaced by the next of its item's `fallbacks` that probes as one.
+    pub async fn download(&self, items: &[DownloadItem]) -> Result<Vec<ChosenImage>> {
+        let output_dir = self.output_dir()?;
+        self.download_to(items, &output_dir).await
+    }
+
+    /// Like `download`, into `output_dir`
+    pub async fn download_to(&self, items: &[DownloadItem], output_dir: &Path) -> Result<Vec<ChosenImage>> {
+        let mut chosen = self.download_batch(items, output_dir).await?;
+
+        // Each fallback is used at most once, so two picks of a query can't both
+        // fall back to the same image
+        let mut used: HashSet<String> = items.iter().map(|i| i.image.download_url.clone()).collect();
+        let mut next_fallback = vec![0; items.len()];
+        loop {
+            let mut retries = Vec::new();
+            for (i, item) in items.iter().enumerate() {
+                if !download_failed(&chosen[i]) {
+                    continue;
+                }
+                while let Some(candidate) = item.fallbacks.get(next_fallback[i]) {
+                    next_fallback[i] += 1;
+                    if used.insert(candidate.download_url.clone())
+                        && download::probe_image(&self.limiter, &candidate.download_url).await.is_ok()
+                    {
+                        // Keep the {index} of the image it replaces
+                        let retry = DownloadItem {
+                            image: candidate.clone(),
+                            fallbacks: Vec::new(),
+                            index: Some(item.index.unwrap_or(i + 1)),
+                            ..item.clone()
+                        };
+                        retries.push((i, retry));
+                        break;
+                    }
+                }
+            }
+            if retries.is_empty() {
+                return Ok(chosen);
+            }
+
+            let (indices, retries): (Vec<usize>, Vec<DownloadItem>) = retries.into_iter().unzip();
+            let outcomes = self.download_batch(&retries, output_dir).await?;
+            for (i, outcome) in indices.into_iter().zip(outcomes) {
+                chosen[i] = outcome;
+            }
+        }
+    }
+
+    async fn download_batch(&self, items: &[DownloadItem], output_dir: &Path) -> Result<Vec<ChosenImage>> {
+        let options = download::DownloadOptions {
+            name_template: self.name_template.clone(),
+            on_conflict: self.on_conflict,
+            quiet: !self.progress_bars,
+        };
+        let outcomes = download::download_images(items, output_dir, &options, &self.limiter).await?;
+
+        Ok(items
+            .iter()
+            .zip(outcomes)
+            .map(|(item, outcome)| {
+                let mut chosen = ChosenImage::new(item.image.clone());
+                match outcome {
+                    Ok(SaveOutcome::Saved(path)) => {
+                        chosen.status = ImageStatus::Saved;
+                        chosen.path = Some(path);
+                    }
+                    Ok(SaveOutcome::Skipped(path)) => {
+                        chosen.status = ImageStatus::Skipped;
+                        chosen.path = Some(path);
+                    }
+                    Err(e) => {
+                        chosen.status = ImageStatus::Failed;
+                        chosen.error = Some(ErrorRecord::from_error(&e));
+                    }
+                }
+                chosen
+            })
+            .collect())
+    }
+}
+
+/// The image host let us down (not e.g. a full disk), so another candidate may do better
+fn download_failed(chosen: &ChosenImage) -> bool {
+    matches!(
+        chosen.error,
+        Some(ErrorRecord::Fetchr(FetchrError::DownloadFailed { .. }))
+    )
+}
@@ -0,0 +1,265 @@
+use std::error::Error as _;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+use crate::tape;
+
+const USER_AGENT: &str = concat!("fetchr/", env!("CARGO_PKG_VERSION"));
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default for a whole request; slow backends such as local LLMs raise it per request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The HTTP client for this run. Built on first use and shared, so every
+/// request goes through the same connection pool, timeouts and user agent.
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| build_client(reqwest::Client::builder().timeout(REQUEST_TIMEOUT)))
+}
+
+/// The client for image downloads. A large image on a slow link may take
+/// longer than `REQUEST_TIMEOUT` in total, so only a stalled read times out.
+pub fn download_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| build_client(reqwest::Client::builder().read_timeout(REQUEST_TIMEOUT)))
+}
+
+fn build_client(builder: reqwest::ClientBuilder) -> reqwest::Client {
+    builder
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// When and how often to repeat a failed request
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Backoff before the first retry; doubles on each retry
+    pub base_delay: Duration,
+    /// Cap on a single backoff, and on how long we'll honour `Retry-After`
+    pub max_delay: Duration,
+    /// Give up once retrying would take longer than this in total
+    pub max_elapsed: Duration,
+}
+
+impl RetryPolicy {
+    /// LLM and search APIs
+    pub const API: RetryPolicy = RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(30),
+        max_elapsed: Duration::from_secs(90),
+    };
+
+    /// Probing search candidates: a host that stays busy is skipped for the
+    /// next candidate rather than waited for
+    pub const PROBE: RetryPolicy = RetryPolicy {
+        max_retries: 2,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(5),
+        max_elapsed: Duration::from_secs(15),
+    };
+
+    /// Image downloads, with the retry count from `download.max_retries`
+    pub fn download(max_retries: u32) -> Self {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_elapsed: Duration::from_secs(180),
+        }
+    }
+
+    /// Exponential backoff with jitter: between half and all of `base * 2^attempt`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_delay);
+        ceiling.mul_f64(jitter())
+    }
+
+    /// How long to wait before retrying after `outcome`, or `None` to stop.
+    ///
+    /// Retries 429 and 5xx gateway errors (waiting for `Retry-After` when the
+    /// server sends one), timeouts, refused connections and connection resets.
+    pub fn next_delay(
+        &self,
+        attempt: u32,
+        started: Instant,
+        outcome: &Result<Response, reqwest::Error>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        let delay = match outcome {
+            Ok(response) if is_retryable_status(response.status()) => match retry_after(response) {
+                Some(wait) if wait > self.max_delay => return None,
+                Some(wait) => wait,
+                None => self.backoff(attempt),
+            },
+            Err(e) if is_transient(e) => self.backoff(attempt),
+            _ => return None,
+        };
+
+        (started.elapsed() + delay <= self.max_elapsed).then_some(delay)
+    }
+}
+
+/// Statuses that mean "try again later" rather than "this won't work"
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Network failures worth another attempt
+pub fn is_transient(error: &reqwest::Error) -> bool {
+    if error.is_timeout() || error.is_connect() {
+        return true;
+    }
+
+    // Connection resets surface as an io::Error somewhere down the source chain
+    let mut source = error.source();
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Parse a `Retry-After` header given in seconds or as an HTTP date
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// A number in [0.5, 1.0) that varies between calls, so that clients backing
+/// off at the same moment don't all retry at the same moment too
+fn jitter() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    0.5 + (nanos % 1000) as f64 / 2000.0
+}
+
+/// Send one request, through the `--record`/`--replay` tape when there is one
+pub async fn execute(request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    match tape::active() {
+        Some(tape) => {
+            let (client, request) = request.build_split();
+            tape.execute(&client, request?).await
+        }
+        None => request.send().await,
+    }
+}
+
+/// Send a request, retrying according to `policy`.
+///
+/// `request` builds a fresh request for every attempt. The final response is
+/// returned whatever its status, so callers turn non-success into their own errors.
+pub async fn send<F>(
+    service: &str,
+    policy: &RetryPolicy,
+    mut request: F,
+) -> Result<Response, reqwest::Error>
+where
+    F: FnMut() -> RequestBuilder,
+{
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+        let outcome = execute(request()).await;
+        match policy.next_delay(attempt, started, &outcome) {
+            Some(delay) => {
+                if matches!(&outcome, Ok(r) if r.status() == StatusCode::TOO_MANY_REQUESTS) {
+                    eprintln!(
+                        "{} rate limited, retrying in {:.1}s...",
+                        service,
+                        delay.as_secs_f64()
+                    );
+                }
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            None => return outcome,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, retry_after: Option<&str>) -> Response {
+        let mut builder = ::http::Response::builder().status(status);
+        if let Some(value) = retry_after {
+            builder = builder.header(reqwest::header::RETRY_AFTER, value);
+        }
+        Response::from(builder.body("").unwrap())
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(retry_after(&response(429, Some("120"))), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(&response(429, Some(" 0 "))), Some(Duration::ZERO));
+        assert_eq!(retry_after(&response(429, None)), None);
+        assert_eq!(retry_after(&response(429, Some("soon"))), None);
+    }
+
+    #[test]
+    fn retry_after_as_a_date() {
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let wait = retry_after(&response(503, Some(&later))).unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+
+        // A date in the past means "now"
+        let past = "Wed, 21 Oct 2015 07:28:00 GMT";
+        assert_eq!(retry_after(&response(503, Some(past))), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn next_delay_follows_the_policy() {
+        let policy = RetryPolicy::PROBE;
+        let started = Instant::now();
+
+        assert_eq!(policy.next_delay(0, started, &Ok(response(429, Some("2")))), Some(Duration::from_secs(2)));
+        // Waiting longer than `max_delay` isn't worth it
+        assert_eq!(policy.next_delay(0, started, &Ok(response(429, Some("60")))), None);
+        assert_eq!(policy.next_delay(policy.max_retries, started, &Ok(response(503, None))), None);
+        assert_eq!(policy.next_delay(0, started, &Ok(response(404, None))), None);
+
+        let backoff = policy.next_delay(1, started, &Ok(response(502, None))).unwrap();
+        assert!(backoff >= Duration::from_millis(500) && backoff <= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::search::Constraints;
+
+/// How a `--file` is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InputFormat {
+    /// Queries separated by commas or newlines; a trailing @N sets the count
+    Text,
+    /// One query per line; commas are part of the query
+    Lines,
+    /// Header row with a `query` column plus optional option columns
+    Csv,
+    /// Array of query strings or objects
+    Json,
+    /// Sequence of query strings or objects
+    Yaml,
+}
+
+impl InputFormat {
+    /// Guess from the file extension, falling back to plain text
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "csv" => InputFormat::Csv,
+            "json" => InputFormat::Json,
+            "yaml" | "yml" => InputFormat::Yaml,
+            _ => InputFormat::Text,
+        }
+    }
+}
+
+/// A single query plus its per-query overrides
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuerySpec {
+    pub query: String,
+    /// Images to fetch for this query (overrides `--count`)
+    #[serde(default)]
+    pub count: Option<usize>,
+    /// Filename template for this query (overrides `--name-template`)
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default, alias = "size")]
+    pub img_size: Option<String>,
+    #[serde(default, alias = "type")]
+    pub img_type: Option<String>,
+    #[serde(default)]
+    pub min_width: Option<u32>,
+    #[serde(default)]
+    pub min_height: Option<u32>,
+}
+
+impl QuerySpec {
+    /// Parse "Tesla logo @3" into the query text and a count override
+    pub fn parse(raw: &str) -> Self {
+        if let Some((query, count)) = raw.rsplit_once('@') {
+            if let Ok(count) = count.trim().parse::<usize>() {
+                if count > 0 && query.trim().len() >= 2 {
+                    return Self {
+                        query: query.trim().to_string(),
+                        count: Some(count),
+                        ..Default::default()
+                    };
+                }
+            }
+        }
+        Self {
+            query: raw.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Size, type and dimension requirements from this row
+    pub fn constraints(&self) -> Constraints {
+        Constraints {
+            img_size: self.img_size.clone(),
+            img_type: self.img_type.clone(),
+            min_width: self.min_width,
+            min_height: self.min_height,
+        }
+    }
+}
+
+pub fn parse_comma_separated(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| s.len() >= 2) // Minimum 2 chars to prevent accidental searches
+        .collect()
+}
+
+/// Read queries from a file, detecting the format from its extension unless `format` is given.
+/// A path of `-` reads standard input.
+pub fn parse_queries_from_file(path: &Path, format: Option<InputFormat>) -> Result<Vec<QuerySpec>> {
+    if path == Path::new("-") {
+        return parse_queries_from_stdin(format);
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read queries from {}", path.display()))?;
+    let format = format.unwrap_or_else(|| InputFormat::from_path(path));
+
+    parse_queries(&content, format)
+        .with_context(|| format!("Failed to parse {} as {:?} input", path.display(), format))
+}
+
+/// Read queries piped into standard input (text format unless `format` is given)
+pub fn parse_queries_from_stdin(format: Option<InputFormat>) -> Result<Vec<QuerySpec>> {
+    let mut content = String::new();
+    std::io::stdin()
+        .read_to_string(&mut content)
+        .context("Failed to read queries from stdin")?;
+    let format = format.unwrap_or(InputFormat::Text);
+
+    parse_queries(&content, format)
+        .with_context(|| format!("Failed to parse stdin as {:?} input", format))
+}
+
+pub fn parse_queries(content: &str, format: InputFormat) -> Result<Vec<QuerySpec>> {
+    let specs: Vec<QuerySpec> = match format {
+        InputFormat::Text => content
+            .lines()
+            .flat_map(parse_comma_separated)
+            .map(|q| QuerySpec::parse(&q))
+            .collect(),
+        InputFormat::Lines => content
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.len() >= 2 && !l.starts_with('#'))
+            .map(QuerySpec::parse)
+            .collect(),
+        InputFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .comment(Some(b'#'))
+                .from_reader(content.as_bytes());
+            reader
+                .deserialize()
+                .enumerate()
+                .map(|(i, row)| row.with_context(|| format!("Invalid row {}", i + 2)))
+                .collect::<Result<_>>()?
+        }
+        InputFormat::Json => rows(serde_json::from_str(content)?)?,
+        InputFormat::Yaml => rows(serde_yaml::from_str(content)?)?,
+    };
+
+    for spec in &specs {
+        if spec.query.trim().len() < 2 {
+            anyhow::bail!("Query \"{}\" is too short (min 2 characters)", spec.query);
+        }
+        if spec.count == Some(0) {
+            anyhow::bail!("Query \"{}\" has count 0", spec.query);
+        }
+    }
+    Ok(specs)
+}
+
+/// JSON and YAML rows may be plain query strings or objects with options
+fn rows(rows: Vec<serde_json::Value>) -> Result<Vec<QuerySpec>> {
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| match row {
+            serde_json::Value::String(query) => Ok(QuerySpec::parse(&query)),
+            row => serde_json::from_value(row).with_context(|| format!("Invalid entry {}", i + 1)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_at_sets_the_count() {
+        let spec = QuerySpec::parse("Tesla logo @3");
+        assert_eq!(spec.query, "Tesla logo");
+        assert_eq!(spec.count, Some(3));
+
+        // Not a count: zero, no number, or nothing left to search for
+        assert_eq!(QuerySpec::parse("cat @0").count, None);
+        assert_eq!(QuerySpec::parse("user@example.com").query, "user@example.com");
+        assert_eq!(QuerySpec::parse("@5").count, None);
+    }
+
+    #[test]
+    fn text_splits_on_commas_and_newlines() {
+        let specs = parse_queries("cat, dog @2\nx, red fox", InputFormat::Text).unwrap();
+        let queries: Vec<_> = specs.iter().map(|s| (s.query.as_str(), s.count)).collect();
+        assert_eq!(queries, [("cat", None), ("dog", Some(2)), ("red fox", None)]);
+    }
+
+    #[test]
+    fn lines_keep_commas_and_skip_comments() {
+        let specs = parse_queries("# brands\nBen & Jerry's, logo\n\nApple @2\n", InputFormat::Lines).unwrap();
+        let queries: Vec<_> = specs.iter().map(|s| (s.query.as_str(), s.count)).collect();
+        assert_eq!(queries, [("Ben & Jerry's, logo", None), ("Apple", Some(2))]);
+    }
+
+    #[test]
+    fn csv_columns() {
+        let csv = "query,count,type,min_width\n\"Nike, Inc. logo\",2,clipart,500\nred fox,,,\n";
+        let specs = parse_queries(csv, InputFormat::Csv).unwrap();
+        assert_eq!(specs[0].query, "Nike, Inc. logo");
+        assert_eq!(specs[0].count, Some(2));
+        assert_eq!(specs[0].img_type.as_deref(), Some("clipart"));
+        assert_eq!(specs[0].min_width, Some(500));
+        assert_eq!(specs[1].query, "red fox");
+        assert_eq!(specs[1].count, None);
+
+        let error = parse_queries("query,colour\ncat,red\n", InputFormat::Csv).unwrap_err();
+        assert!(format!("{:#}", error).contains("Invalid row 2"));
+    }
+
+    #[test]
+    fn json_and_yaml_rows() {
+        let json = r#"["cat @2", {"query": "dog", "size": "large", "filename": "{query}-{n}"}]"#;
+        let specs = parse_queries(json, InputFormat::Json).unwrap();
+        assert_eq!((specs[0].query.as_str(), specs[0].count), ("cat", Some(2)));
+        assert_eq!(specs[1].img_size.as_deref(), Some("large"));
+        assert_eq!(specs[1].filename.as_deref(), Some("{query}-{n}"));
+
+        let yaml = "- cat\n- query: dog\n  count: 4\n  min_height: 300\n";
+        let specs = parse_queries(yaml, InputFormat::Yaml).unwrap();
+        assert_eq!(specs[0].query, "cat");
+        assert_eq!((specs[1].count, specs[1].min_height), (Some(4), Some(300)));
+
+        assert!(parse_queries(r#"[{"query": "dog", "colour": "red"}]"#, InputFormat::Json).is_err());
+    }
+
+    #[test]
+    fn rejects_short_queries_and_zero_counts() {
+        assert!(parse_queries(r#"[{"query": "x"}]"#, InputFormat::Json).is_err());
+        assert!(parse_queries(r#"[{"query": "cat", "count": 0}]"#, InputFormat::Json).is_err());
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(InputFormat::from_path(Path::new("q.CSV")), InputFormat::Csv);
+        assert_eq!(InputFormat::from_path(Path::new("q.yml")), InputFormat::Yaml);
+        assert_eq!(InputFormat::from_path(Path::new("queries")), InputFormat::Text);
+    }
+}
@@ -0,0 +1,38 @@
+//! AI-powered image asset fetcher.
+//!
+//! [`Fetcher`] expands short descriptions like "Tesla logo" into image searches
+//! with an LLM, picks results that serve real images and downloads them. The
+//! `fetchr` command line tool is a thin layer over it.
+
+mod ai;
+mod auto;
+mod cache;
+mod download;
+mod errors;
+mod fetcher;
+mod http;
+mod input;
+mod manifest;
+mod mcp;
+mod output;
+mod preview;
+mod search;
+mod serve;
+mod sniff;
+mod tape;
+
+pub mod config;
+pub mod feedback;
+
+#[doc(hidden)]
+pub mod cli;
+
+pub use ai::{ExpandedQuery, QueryExpander};
+pub use auto::AutoDecision;
+pub use cache::CacheMode;
+pub use download::{ConflictPolicy, DownloadItem};
+pub use errors::{FetchrError, ServiceError};
+pub use fetcher::{Event, Fetcher, FetcherBuilder, FindResult, Pick, Progress, ProgressFn};
+pub use input::QuerySpec;
+pub use output::{ChosenImage, ErrorRecord, ImageStatus, QueryRecord};
+pub use search::{Constraints, ImageResult, ImageSearchProvider};
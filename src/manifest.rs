@@ -0,0 +1,469 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ai::{self, ExpandedQuery, QueryExpander};
+use crate::cache::{self, Cache, CacheMode};
+use crate::config::{self, Config};
+use crate::download;
+use crate::http;
+use crate::search::{self, Constraints, ImageSearchProvider};
+
+/// Asset manifest, looked up from the working directory upwards
+pub const MANIFEST_FILE: &str = "fetchr.toml";
+/// Resolved assets, written next to the manifest
+pub const LOCK_FILE: &str = "fetchr.lock";
+
+const LOCK_VERSION: u32 = 1;
+
+/// The assets a project needs, as checked into its repo
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Where assets are saved, relative to the manifest (default: next to it)
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    #[serde(default)]
+    pub assets: BTreeMap<String, AssetSpec>,
+}
+
+/// One `[assets.<name>]` entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetSpec {
+    pub query: String,
+    #[serde(default = "default_count")]
+    pub count: usize,
+    /// File path without extension, relative to `output_dir` (default: the asset name).
+    /// With `count` above 1, "-1", "-2", ... are appended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Size filter (large, medium, icon), overriding the LLM's choice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    /// Type filter (photo, clipart, lineart, face), overriding the LLM's choice
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_height: Option<u32>,
+}
+
+fn default_count() -> usize {
+    1
+}
+
+impl AssetSpec {
+    fn constraints(&self) -> Constraints {
+        Constraints {
+            img_size: self.size.clone(),
+            img_type: self.kind.clone(),
+            min_width: self.min_width,
+            min_height: self.min_height,
+        }
+    }
+}
+
+/// `fetchr.lock`: exactly which files each asset resolved to
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default, rename = "asset")]
+    pub assets: Vec<LockedAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedAsset {
+    pub name: String,
+    /// The manifest entry this was resolved from; a change triggers a re-resolve
+    pub spec: AssetSpec,
+    pub expanded: ExpandedQuery,
+    #[serde(default)]
+    pub files: Vec<LockedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedFile {
+    /// Relative to the manifest directory, with `/` separators
+    pub path: String,
+    pub url: String,
+    pub sha256: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Options for `fetchr sync`
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Manifest to use instead of searching for `fetchr.toml`
+    pub manifest: Option<PathBuf>,
+    /// Asset names to re-resolve even if they are locked
+    pub update: Vec<String>,
+    pub no_ai: bool,
+    pub provider: Option<String>,
+    pub cache: CacheMode,
+}
+
+/// Find the nearest manifest in the working directory or its parents
+fn find_manifest() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .find(|path| path.is_file())
+}
+
+pub fn load_manifest(path: &Path) -> Result<Manifest> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read manifest from {:?}", path))?;
+    let manifest: Manifest =
+        toml::from_str(&content).with_context(|| format!("Failed to parse manifest {:?}", path))?;
+
+    for (name, spec) in &manifest.assets {
+        if spec.query.trim().len() < 2 {
+            anyhow::bail!("Asset '{}' needs a query of at least 2 characters", name);
+        }
+        if spec.count == 0 {
+            anyhow::bail!("Asset '{}' has count = 0", name);
+        }
+    }
+    Ok(manifest)
+}
+
+fn load_lock(path: &Path) -> Result<Lockfile> {
+    if !path.exists() {
+        return Ok(Lockfile {
+            version: LOCK_VERSION,
+            assets: Vec::new(),
+        });
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read lockfile from {:?}", path))?;
+    let lock: Lockfile =
+        toml::from_str(&content).with_context(|| format!("Failed to parse lockfile {:?}", path))?;
+    if lock.version > LOCK_VERSION {
+        anyhow::bail!(
+            "{:?} was written by a newer fetchr (lock version {}); please upgrade",
+            path,
+            lock.version
+        );
+    }
+    Ok(lock)
+}
+
+fn save_lock(path: &Path, lock: &Lockfile) -> Result<()> {
+    let content = toml::to_string_pretty(lock).context("Failed to serialize lockfile")?;
+    let content = format!(
+        "# Generated by `fetchr sync`. Commit this file; don't edit it by hand.\n\n{}",
+        content
+    );
+    fs::write(path, content).with_context(|| format!("Failed to write lockfile to {:?}", path))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Real pixel dimensions, or `None` for formats we can't decode (e.g. SVG)
+fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Path of the `n`th file of an asset, relative to the manifest directory, without extension
+fn asset_stem(output_dir: &str, name: &str, spec: &AssetSpec, n: usize) -> String {
+    let base = spec.path.as_deref().unwrap_or(name).trim_matches('/');
+    let stem = if spec.count > 1 {
+        format!("{}-{}", base, n)
+    } else {
+        base.to_string()
+    };
+    if output_dir.is_empty() || output_dir == "." {
+        stem
+    } else {
+        format!("{}/{}", output_dir.trim_end_matches('/'), stem)
+    }
+}
+
+/// Resolve a manifest-relative path, refusing anything that escapes the project
+fn project_path(root: &Path, relative: &str) -> Result<PathBuf> {
+    let relative = Path::new(relative);
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        anyhow::bail!("Asset path {:?} must stay inside the project", relative);
+    }
+    Ok(root.join(relative))
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+    fs::write(path, bytes).with_context(|| format!("Failed to save image to {:?}", path))
+}
+
+/// What `sync` did with one asset
+enum SyncAction {
+    UpToDate,
+    Restored(usize),
+    Resolved(usize),
+}
+
+/// Everything `sync` needs while working through the manifest
+struct SyncContext<'a> {
+    root: PathBuf,
+    output_dir: String,
+    config: &'a Config,
+    client: reqwest::Client,
+    limiter: download::HostLimiter,
+    expander: Option<Box<dyn QueryExpander>>,
+    provider: Option<Box<dyn ImageSearchProvider>>,
+    options: &'a SyncOptions,
+}
+
+impl SyncContext<'_> {
+    /// Providers are only built once something actually needs resolving, so a
+    /// fully locked project syncs without any API keys
+    fn providers(&mut self) -> Result<(&dyn QueryExpander, &dyn ImageSearchProvider)> {
+        if self.expander.is_none() || self.provider.is_none() {
+            let cache = Cache::open(self.config, self.options.cache)?;
+            let expander = ai::expander_from_config(self.config, self.options.no_ai)?;
+            let provider = search::provider_from_config(self.config, self.options.provider.as_deref())?;
+            self.expander = Some(cache::expander(expander, self.config, &cache));
+            self.provider = Some(cache::provider(provider, &cache));
+        }
+        Ok((
+            self.expander.as_deref().unwrap(),
+            self.provider.as_deref().unwrap(),
+        ))
+    }
+
+    /// Make sure every locked file is on disk with the locked contents
+    async fn restore(&self, name: &str, spec: &AssetSpec, locked: &mut LockedAsset) -> Result<SyncAction> {
+        let mut fetched = 0;
+        for (i, file) in locked.files.iter_mut().enumerate() {
+            // Follow manifest changes to `path` and `output_dir` without re-resolving
+            let ext = Path::new(&file.path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_else(|| "img".to_string());
+            let expected = format!("{}.{}", asset_stem(&self.output_dir, name, spec, i + 1), ext);
+            let target = project_path(&self.root, &expected)?;
+
+            if let Ok(existing) = fs::read(&target) {
+                if sha256_hex(&existing) == file.sha256 {
+                    file.path = expected;
+                    continue;
+                }
+            }
+
+            let (bytes, _) = download::fetch_image(&self.client, &self.limiter, &file.url)
+                .await
+                .with_context(|| format!("Failed to fetch locked file {}", file.url))?;
+            if sha256_hex(&bytes) != file.sha256 {
+                anyhow::bail!(
+                    "{} no longer serves the locked image; run `fetchr sync --update {}`",
+                    file.url,
+                    name
+                );
+            }
+            write_file(&target, &bytes)?;
+            file.path = expected;
+            fetched += 1;
+        }
+
+        Ok(if fetched == 0 {
+            SyncAction::UpToDate
+        } else {
+            SyncAction::Restored(fetched)
+        })
+    }
+
+    /// Search for the asset from scratch and lock what we find
+    async fn resolve(&mut self, name: &str, spec: &AssetSpec) -> Result<LockedAsset> {
+        let limit = self.config.defaults.limit.max(spec.count);
+        let (expander, provider) = self.providers()?;
+
+        let constraints = spec.constraints();
+
+        let mut expanded = ai::expand_prompt(&spec.query, expander).await?;
+        constraints.apply(&mut expanded);
+
+        let results = provider.search_images(&expanded, &spec.query, limit).await?;
+
+        let mut files: Vec<LockedFile> = Vec::new();
+        for result in results {
+            if files.len() >= spec.count {
+                break;
+            }
+            if files.iter().any(|f| f.url == result.download_url) || !constraints.fits(result.width, result.height) {
+                continue;
+            }
+
+            let Ok((bytes, format)) = download::fetch_image(&self.client, &self.limiter, &result.download_url).await else {
+                continue;
+            };
+            let (width, height) = image_dimensions(&bytes).unwrap_or((result.width, result.height));
+            if !constraints.fits(width, height) {
+                continue;
+            }
+
+            let path = format!(
+                "{}.{}",
+                asset_stem(&self.output_dir, name, spec, files.len() + 1),
+                format.extension()
+            );
+            write_file(&project_path(&self.root, &path)?, &bytes)?;
+
+            files.push(LockedFile {
+                path,
+                url: result.download_url,
+                sha256: sha256_hex(&bytes),
+                width,
+                height,
+            });
+        }
+
+        if files.is_empty() {
+            anyhow::bail!("No usable images found for \"{}\"", spec.query);
+        }
+
+        Ok(LockedAsset {
+            name: name.to_string(),
+            spec: spec.clone(),
+            expanded,
+            files,
+        })
+    }
+}
+
+/// Fetch whatever the manifest lists that isn't already on disk, and update the lockfile.
+///
+/// Locked assets are restored from their recorded URL and verified against the
+/// recorded hash; new, changed and `--update`d assets are searched for again.
+pub async fn sync(options: &SyncOptions) -> Result<()> {
+    let manifest_path = match &options.manifest {
+        Some(path) => path.clone(),
+        None => find_manifest().with_context(|| {
+            format!("No {} found in this directory or its parents", MANIFEST_FILE)
+        })?,
+    };
+    let manifest = load_manifest(&manifest_path)?;
+    let root = manifest_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let lock_path = root.join(LOCK_FILE);
+    let lock = load_lock(&lock_path)?;
+
+    for name in &options.update {
+        if !manifest.assets.contains_key(name) {
+            anyhow::bail!("No asset named '{}' in {}", name, manifest_path.display());
+        }
+    }
+
+    let output_dir = match &manifest.output_dir {
+        Some(dir) => dir.replace('\\', "/"),
+        None => String::new(),
+    };
+    project_path(&root, if output_dir.is_empty() { "." } else { &output_dir })?;
+
+    let config = config::load()?;
+    let mut ctx = SyncContext {
+        root,
+        output_dir,
+        config: &config,
+        client: http::download_client().clone(),
+        limiter: download::HostLimiter::new(download::DownloadLimits::from_config(&config)),
+        expander: None,
+        provider: None,
+        options,
+    };
+
+    println!(
+        "  \x1b[1mSyncing {} asset{} from {}\x1b[0m\n",
+        manifest.assets.len(),
+        if manifest.assets.len() == 1 { "" } else { "s" },
+        manifest_path.display()
+    );
+
+    let mut locked: BTreeMap<String, LockedAsset> =
+        lock.assets.into_iter().map(|a| (a.name.clone(), a)).collect();
+    let mut failed = 0;
+
+    for (name, spec) in &manifest.assets {
+        let reuse = !options.update.contains(name)
+            && locked.get(name).is_some_and(|l| &l.spec == spec && !l.files.is_empty());
+
+        let action = if reuse {
+            let entry = locked.get_mut(name).unwrap();
+            ctx.restore(name, spec, entry).await
+        } else {
+            match ctx.resolve(name, spec).await {
+                Ok(entry) => {
+                    let count = entry.files.len();
+                    locked.insert(name.clone(), entry);
+                    Ok(SyncAction::Resolved(count))
+                }
+                Err(e) => Err(e),
+            }
+        };
+
+        match action {
+            Ok(SyncAction::UpToDate) => {
+                println!("  \x1b[90m=\x1b[0m {} \x1b[90mup to date\x1b[0m", name);
+            }
+            Ok(SyncAction::Restored(n)) => {
+                println!("  \x1b[32m↓\x1b[0m {} restored {} file{} from lock", name, n, if n == 1 { "" } else { "s" });
+            }
+            Ok(SyncAction::Resolved(n)) => {
+                let entry = &locked[name];
+                let shortfall = if n < spec.count {
+                    format!(" \x1b[33m({}/{} found)\x1b[0m", n, spec.count)
+                } else {
+                    String::new()
+                };
+                println!(
+                    "  \x1b[32m+\x1b[0m {} resolved \"{}\"{}",
+                    name, entry.expanded.query, shortfall
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                println!("  \x1b[31m✗\x1b[0m {}: {:#}", name, e);
+            }
+        }
+    }
+
+    // Entries removed from the manifest drop out of the lock; their files are left alone
+    locked.retain(|name, _| manifest.assets.contains_key(name));
+    save_lock(
+        &lock_path,
+        &Lockfile {
+            version: LOCK_VERSION,
+            assets: locked.into_values().collect(),
+        },
+    )?;
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} asset{} failed to sync",
+            failed,
+            if failed == 1 { "" } else { "s" }
+        );
+    }
+
+    println!("\n  \x1b[32m✓\x1b[0m Lockfile written to {}", lock_path.display());
+    Ok(())
+}
@@ -0,0 +1,368 @@
+//! `fetchr mcp`: a Model Context Protocol server on stdin/stdout, so coding
+//! assistants can search for images, save them into a project and rate them.
+//!
+//! Messages are newline-delimited JSON-RPC 2.0. Requests are handled
+//! concurrently; stdout carries nothing but replies, and logs go to stderr.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+use crate::cache::CacheMode;
+use crate::config::{self, Config};
+use crate::download::{self, DownloadItem};
+use crate::errors::{self, FetchrError};
+use crate::feedback::{self, ApiFeedbackEntry, ApiRating};
+use crate::fetcher::{Fetcher, FetcherBuilder, Pick};
+use crate::input::QuerySpec;
+use crate::output::{ErrorRecord, ImageStatus};
+use crate::search::ImageResult;
+
+/// Newest first; we answer with the client's version when we know it
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Options for `fetchr mcp`
+#[derive(Debug, Clone, Default)]
+pub struct McpOptions {
+    pub no_ai: bool,
+    pub provider: Option<String>,
+    pub cache: CacheMode,
+}
+
+struct Server {
+    config: Config,
+    options: McpOptions,
+}
+
+/// A JSON-RPC error
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Serve MCP on stdin/stdout until stdin closes
+pub async fn run(options: McpOptions) -> Result<()> {
+    let config = config::load().map_err(errors::config_error)?;
+    let server = Arc::new(Server {
+        config,
+        options,
+    });
+    eprintln!("fetchr MCP server ready on stdio");
+
+    // Replies are written as they finish; the channel closes once stdin is
+    // done and every request in flight has answered
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let reader = tokio::spawn(async move {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let server = server.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Some(reply) = server.handle(&line).await {
+                    let _ = tx.send(reply);
+                }
+            });
+        }
+        anyhow::Ok(())
+    });
+
+    let mut stdout = tokio::io::stdout();
+    while let Some(reply) = rx.recv().await {
+        let mut line = serde_json::to_vec(&reply)?;
+        line.push(b'\n');
+        stdout.write_all(&line).await?;
+        stdout.flush().await?;
+    }
+    reader.await?.context("Failed to read from stdin")
+}
+
+impl Server {
+    /// The reply to one message, or `None` for notifications
+    async fn handle(&self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return Some(error_reply(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+        // Notifications (and stray responses) carry no id and get no reply
+        let id = message.get("id")?.clone();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return Some(error_reply(id, RpcError::new(INVALID_REQUEST, "Missing method")));
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => self.call_tool(params).await,
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_reply(id, e),
+        })
+    }
+
+    async fn call_tool(&self, params: Value) -> Result<Value, RpcError> {
+        let name = params.get("name").and_then(Value::as_str).unwrap_or_default();
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+        eprintln!("tools/call {}", name);
+
+        let outcome = match name {
+            "search_images" => self.search_images(arguments).await,
+            "download_image" => self.download_image(arguments).await,
+            "rate_result" => self.rate_result(arguments).await,
+            _ => return Err(RpcError::new(INVALID_PARAMS, format!("Unknown tool {}", name))),
+        };
+        // Failures go back to the model as tool output it can act on
+        Ok(match outcome {
+            Ok(content) => tool_result(content, false),
+            Err(e) => tool_result(json!({ "error": ErrorRecord::from_error(&e) }), true),
+        })
+    }
+
+    fn fetcher(&self, no_ai: Option<bool>) -> FetcherBuilder {
+        let mut builder = Fetcher::builder()
+            .config(self.config.clone())
+            .cache(self.options.cache)
+            .no_ai(no_ai.unwrap_or(self.options.no_ai));
+        if let Some(provider) = &self.options.provider {
+            builder = builder.provider_name(provider);
+        }
+        builder
+    }
+
+    async fn search_images(&self, arguments: Value) -> Result<Value> {
+        let args: SearchArgs = parse_arguments(arguments)?;
+        let mut spec = QuerySpec::parse(&args.query);
+        if spec.query.trim().len() < 2 {
+            anyhow::bail!("Query \"{}\" is too short (min 2 characters)", spec.query);
+        }
+        spec.count = args.count.or(spec.count);
+        spec.img_type = args.img_type;
+        spec.img_size = args.img_size;
+        spec.min_width = args.min_width;
+        spec.min_height = args.min_height;
+
+        let fetcher = self.fetcher(args.no_ai).auto(args.auto).build()?;
+        let found = fetcher.find_spec(&spec).await?;
+        if found.picks.is_empty() {
+            return Err(FetchrError::NoResults { query: found.query }.into());
+        }
+        Ok(json!({
+            "query": found.query,
+            "expanded": found.expanded,
+            "images": found.picks,
+        }))
+    }
+
+    async fn download_image(&self, arguments: Value) -> Result<Value> {
+        let args: DownloadArgs = parse_arguments(arguments)?;
+        let fetcher = self.fetcher(None).build()?;
+        // The client may be a model acting on untrusted text, so it only gets
+        // to pick a folder inside the project we were started in
+        let output_dir = match &args.output_dir {
+            Some(dir) => download::confined_dir(&std::env::current_dir()?, dir)?,
+            None => fetcher.output_dir()?,
+        };
+
+        let item = DownloadItem {
+            image: args.image,
+            pick: 1,
+            picks: 1,
+            name_template: args.filename,
+            fallbacks: Vec::new(),
+            index: None,
+        };
+        let saved = fetcher
+            .download_to(&[item], &output_dir)
+            .await?
+            .pop()
+            .context("Nothing was downloaded")?;
+        match (saved.status, saved.error) {
+            (ImageStatus::Failed, Some(ErrorRecord::Fetchr(e))) => Err(e.into()),
+            (ImageStatus::Failed, error) => {
+                anyhow::bail!("Download failed: {}", error.map(|e| e.to_string()).unwrap_or_default())
+            }
+            (status, _) => Ok(json!({
+                "status": status,
+                // Relative to our working directory, which the client may not know
+                "path": saved.path.map(std::path::absolute).transpose()?,
+                "width": saved.image.width,
+                "height": saved.image.height,
+                "download_url": saved.image.download_url,
+                "title": saved.image.title,
+                "source_query": saved.image.source_query,
+            })),
+        }
+    }
+
+    async fn rate_result(&self, arguments: Value) -> Result<Value> {
+        let args: RateArgs = parse_arguments(arguments)?;
+        let entry = args.image.feedback(args.rating.into(), false);
+        feedback::append_entry(entry.clone())?;
+        Ok(json!({ "saved": ApiFeedbackEntry::from(entry) }))
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    query: String,
+    count: Option<usize>,
+    img_type: Option<String>,
+    img_size: Option<String>,
+    min_width: Option<u32>,
+    min_height: Option<u32>,
+    #[serde(default)]
+    auto: bool,
+    no_ai: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct DownloadArgs {
+    /// An image from `search_images`
+    image: ImageResult,
+    output_dir: Option<PathBuf>,
+    filename: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RateArgs {
+    /// An image from `search_images`, with the expanded query that found it
+    image: Pick,
+    rating: ApiRating,
+}
+
+fn parse_arguments<T: DeserializeOwned>(arguments: Value) -> Result<T> {
+    serde_json::from_value(arguments).context("Invalid tool arguments")
+}
+
+fn error_reply(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// Tool output as text for every client, and as structured content for newer ones
+fn tool_result(content: Value, is_error: bool) -> Value {
+    json!({
+        "content": [{
+            "type": "text",
+            "text": serde_json::to_string_pretty(&content).unwrap_or_default(),
+        }],
+        "structuredContent": content,
+        "isError": is_error,
+    })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "fetchr", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Find images with search_images, save the ones you want into the project \
+                         with download_image, and rate_result good or bad picks so later searches improve.",
+    })
+}
+
+fn tools() -> Value {
+    let image = json!({
+        "type": "object",
+        "description": "An entry of `images` from search_images, passed back unchanged",
+        "properties": {
+            "id": { "type": "string" },
+            "title": { "type": "string" },
+            "download_url": { "type": "string" },
+            "width": { "type": "integer" },
+            "height": { "type": "integer" },
+            "source_query": { "type": "string" },
+            "expanded": { "type": "object" },
+        },
+        "required": ["id", "title", "download_url", "width", "height", "source_query", "expanded"],
+    });
+    json!([
+        {
+            "name": "search_images",
+            "description": "Search the web for images matching a short description such as \"Tesla logo\". \
+                            The query is expanded into an optimized image search, and only results that \
+                            serve real images are returned, best first, with their source URL and dimensions.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "What to find, e.g. \"Tesla logo\" or \"mountain sunrise\"" },
+                    "count": { "type": "integer", "minimum": 1, "description": "Images wanted (default 1)" },
+                    "img_type": { "type": "string", "enum": ["photo", "clipart", "lineart", "face"] },
+                    "img_size": { "type": "string", "enum": ["large", "medium", "icon"] },
+                    "min_width": { "type": "integer", "description": "Minimum width in pixels" },
+                    "min_height": { "type": "integer", "description": "Minimum height in pixels" },
+                    "auto": { "type": "boolean", "description": "Reformulate and search again when nothing suitable turns up" },
+                    "no_ai": { "type": "boolean", "description": "Expand the query with keyword rules instead of an LLM" },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "download_image",
+            "description": "Save an image from search_images to disk. Returns the saved path, dimensions and source URL.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "image": image,
+                    "output_dir": {
+                        "type": "string",
+                        "description": "Directory to save into, e.g. the project's assets folder; a relative path \
+                                        inside the server's working directory",
+                    },
+                    "filename": {
+                        "type": "string",
+                        "description": "File name without extension; may use {query}, {date}, {width}, {height}, \
+                                        {domain} or {title}, and / for subfolders (default: the query)",
+                    },
+                },
+                "required": ["image"],
+            },
+        },
+        {
+            "name": "rate_result",
+            "description": "Record whether an image from search_images was a good match. Ratings steer how \
+                            future queries are expanded.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "image": image,
+                    "rating": { "type": "string", "enum": ["thumbs_up", "thumbs_down", "skip"] },
+                },
+                "required": ["image", "rating"],
+            },
+        },
+    ])
+}
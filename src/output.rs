@@ -0,0 +1,264 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::ai::ExpandedQuery;
+use crate::auto::AutoDecision;
+use crate::errors::FetchrError;
+use crate::search::ImageResult;
+
+/// How `cmd_find` reports its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Coloured text with spinners and prompts
+    #[default]
+    Text,
+    /// One JSON document with every query, written when the run finishes
+    Json,
+    /// One JSON object per line, one line per query
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Structured formats own stdout, so banners, spinners and prompts are suppressed
+    pub fn is_structured(self) -> bool {
+        self != OutputFormat::Text
+    }
+}
+
+/// When a finished batch counts as failed (`--fail-on`), for CI jobs that
+/// need a non-zero exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum FailOn {
+    /// Any query failed
+    Any,
+    /// Every query failed
+    #[default]
+    All,
+    /// Never fail once the batch has run
+    None,
+}
+
+impl FailOn {
+    /// Exit status for a batch in which `failed` of `total` queries failed
+    pub fn status(self, failed: usize, total: usize) -> ExitStatus {
+        let counts = match self {
+            FailOn::Any => failed > 0,
+            FailOn::All => failed > 0 && failed == total,
+            FailOn::None => false,
+        };
+        if !counts {
+            ExitStatus::Success
+        } else if failed == total {
+            ExitStatus::TotalFailure
+        } else {
+            ExitStatus::PartialFailure
+        }
+    }
+}
+
+/// Process exit status, so scripts can tell kinds of failure apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    Success = 0,
+    /// Anything unexpected, e.g. an unreadable input file
+    Error = 1,
+    /// Some queries failed (`--fail-on any`)
+    PartialFailure = 3,
+    /// Every query failed
+    TotalFailure = 4,
+    /// Missing API key or an invalid config file
+    ConfigError = 5,
+    /// Declined at a confirmation prompt
+    Cancelled = 130,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Status for a run that ended in an error
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<FetchrError>() {
+            Some(FetchrError::Config { .. }) => ExitStatus::ConfigError,
+            _ => ExitStatus::Error,
+        }
+    }
+}
+
+/// An error in machine-readable form. Known failures keep their kind, service,
+/// status and hint as separate fields.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ErrorRecord {
+    Fetchr(FetchrError),
+    Other { kind: &'static str, message: String },
+}
+
+impl fmt::Display for ErrorRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorRecord::Fetchr(e) => write!(f, "{}", e),
+            ErrorRecord::Other { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl ErrorRecord {
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<FetchrError>() {
+            Some(known) => ErrorRecord::Fetchr(known.clone()),
+            None => ErrorRecord::Other {
+                kind: "other",
+                message: format!("{:#}", error),
+            },
+        }
+    }
+}
+
+/// What happened to a chosen image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageStatus {
+    /// Found but the download was not run (e.g. cancelled)
+    NotDownloaded,
+    Saved,
+    /// A file with the same name existed and `on_conflict = "skip"`
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChosenImage {
+    #[serde(flatten)]
+    pub image: ImageResult,
+    pub status: ImageStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorRecord>,
+}
+
+impl ChosenImage {
+    pub fn new(image: ImageResult) -> Self {
+        Self {
+            image,
+            status: ImageStatus::NotDownloaded,
+            path: None,
+            error: None,
+        }
+    }
+}
+
+/// Everything fetchr did for one input query
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryRecord {
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expanded: Option<ExpandedQuery>,
+    pub candidates: Vec<ImageResult>,
+    pub chosen: Vec<ChosenImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorRecord>,
+    /// Attempts, reformulations and retries, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub decisions: Vec<AutoDecision>,
+}
+
+impl QueryRecord {
+    /// The query errored, found nothing, or every chosen image failed to download
+    pub fn failed(&self) -> bool {
+        self.error.is_some()
+            || (!self.chosen.is_empty()
+                && self.chosen.iter().all(|c| c.status == ImageStatus::Failed))
+    }
+}
+
+#[derive(Serialize)]
+struct RunRecord<'a> {
+    output_dir: &'a Path,
+    queries: &'a [QueryRecord],
+}
+
+/// Write the records to stdout in the requested structured format
+pub fn emit(format: OutputFormat, output_dir: &Path, records: &[QueryRecord]) -> Result<()> {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            let run = RunRecord {
+                output_dir,
+                queries: records,
+            };
+            println!("{}", serde_json::to_string_pretty(&run)?);
+        }
+        OutputFormat::Ndjson => {
+            for record in records {
+                println!("{}", serde_json::to_string(record)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Report a run-ending error on stdout so scripts parsing the output still get JSON
+pub fn emit_fatal(format: OutputFormat, error: &anyhow::Error) {
+    #[derive(Serialize)]
+    struct Fatal {
+        error: ErrorRecord,
+    }
+
+    let fatal = Fatal {
+        error: ErrorRecord::from_error(error),
+    };
+    let json = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(&fatal),
+        _ => serde_json::to_string(&fatal),
+    };
+    if let Ok(json) = json {
+        println!("{}", json);
+    }
+}
+
+/// Everything about one run, written by `--report`
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: ExitStatus,
+    pub exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<&'a Path>,
+    pub queries: &'a [QueryRecord],
+    /// Set when the run ended in an error rather than finishing the batch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorRecord>,
+}
+
+pub fn write_report(path: &Path, report: &Report) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+    std::fs::write(path, json + "\n")
+        .with_context(|| format!("Failed to write report to {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fail_on() {
+        assert_eq!(FailOn::Any.status(0, 3), ExitStatus::Success);
+        assert_eq!(FailOn::Any.status(1, 3), ExitStatus::PartialFailure);
+        assert_eq!(FailOn::Any.status(3, 3), ExitStatus::TotalFailure);
+
+        assert_eq!(FailOn::All.status(1, 3), ExitStatus::Success);
+        assert_eq!(FailOn::All.status(3, 3), ExitStatus::TotalFailure);
+        assert_eq!(FailOn::All.status(0, 0), ExitStatus::Success);
+
+        assert_eq!(FailOn::None.status(3, 3), ExitStatus::Success);
+    }
+}
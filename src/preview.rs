@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+use crate::download::HostLimiter;
+use crate::http::{self, RetryPolicy};
+
+/// Largest preview in terminal columns
+const PREVIEW_COLS: u32 = 24;
+/// Largest preview in terminal rows, so tall images don't scroll the picker away
+const PREVIEW_ROWS: u32 = 12;
+/// Approximate pixel width of a terminal cell, for protocols sized in pixels
+const CELL_PX: u32 = 10;
+/// Approximate pixel height of a terminal cell
+const CELL_HEIGHT_PX: u32 = 20;
+/// Don't pull huge originals just to draw a thumbnail
+const MAX_PREVIEW_BYTES: usize = 8 * 1024 * 1024;
+
+/// How previews should be drawn, as chosen with `--preview`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PreviewMode {
+    /// Pick the best protocol the terminal supports
+    Auto,
+    /// Kitty graphics protocol (kitty, WezTerm, Ghostty, Konsole)
+    Kitty,
+    /// iTerm2 inline images (iTerm2, WezTerm, mintty)
+    Iterm,
+    /// DEC Sixel graphics (foot, mlterm, xterm -ti vt340)
+    Sixel,
+    /// Unicode half blocks with true colour; works almost everywhere
+    Blocks,
+    /// No previews
+    Off,
+}
+
+/// A concrete way of drawing images in this terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+    Blocks,
+}
+
+/// Resolve the user's choice against the current terminal. Returns `None` when
+/// previews are off or stdout isn't a terminal.
+pub fn resolve(mode: PreviewMode) -> Option<Protocol> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    match mode {
+        PreviewMode::Off => None,
+        PreviewMode::Kitty => Some(Protocol::Kitty),
+        PreviewMode::Iterm => Some(Protocol::Iterm),
+        PreviewMode::Sixel => Some(Protocol::Sixel),
+        PreviewMode::Blocks => Some(Protocol::Blocks),
+        PreviewMode::Auto => Some(detect()),
+    }
+}
+
+/// Guess the best protocol from the environment
+fn detect() -> Protocol {
+    let env = |key: &str| std::env::var(key).unwrap_or_default().to_lowercase();
+    let term = env("TERM");
+    let program = env("TERM_PROGRAM");
+
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || term.contains("kitty")
+        || term.contains("ghostty")
+        || program == "ghostty"
+    {
+        Protocol::Kitty
+    } else if program == "iterm.app" || program == "wezterm" || std::env::var_os("LC_TERMINAL").is_some_and(|t| t == "iTerm2") {
+        Protocol::Iterm
+    } else if term.contains("sixel") || term == "foot" || term.starts_with("foot-") || term == "mlterm" {
+        Protocol::Sixel
+    } else {
+        Protocol::Blocks
+    }
+}
+
+/// Download the image at `url` for previewing, within the run's host limits
+pub async fn fetch(limiter: &HostLimiter, url: &str) -> Result<Vec<u8>> {
+    let request = || http::client().get(url).timeout(Duration::from_secs(10));
+    let (mut response, _ticket) = limiter
+        .send(url, &RetryPolicy::PROBE, request)
+        .await
+        .context("Failed to fetch preview")?;
+    if !response.status().is_success() {
+        anyhow::bail!("HTTP {}", response.status().as_u16());
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("Failed to read preview")? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_PREVIEW_BYTES {
+            anyhow::bail!("image too large to preview");
+        }
+    }
+    Ok(bytes)
+}
+
+/// Fetch and render a preview, returning the escape sequences to print.
+/// Every line is prefixed with `indent`.
+pub async fn load(limiter: &HostLimiter, url: &str, protocol: Protocol, indent: &str) -> Result<String> {
+    let bytes = fetch(limiter, url).await?;
+    render(&bytes, protocol, indent)
+}
+
+/// Render image bytes for the given protocol
+pub fn render(bytes: &[u8], protocol: Protocol, indent: &str) -> Result<String> {
+    let image = image::load_from_memory(bytes).context("unsupported image format")?;
+
+    Ok(match protocol {
+        Protocol::Kitty => format!("{}{}", indent, kitty(&image)?),
+        Protocol::Iterm => format!("{}{}", indent, iterm(&image)?),
+        Protocol::Sixel => format!(
+            "{}{}",
+            indent,
+            sixel(&flatten(&image, PREVIEW_COLS * CELL_PX, PREVIEW_ROWS * CELL_HEIGHT_PX))
+        ),
+        // Each cell holds two pixels, one above the other
+        Protocol::Blocks => blocks(&flatten(&image, PREVIEW_COLS, PREVIEW_ROWS * 2), indent),
+    })
+}
+
+/// Whether the preview's height rather than its width hits the limit
+fn is_tall(image: &DynamicImage) -> bool {
+    let (w, h) = image.dimensions();
+    h as u64 * (PREVIEW_COLS * CELL_PX) as u64 > w as u64 * (PREVIEW_ROWS * CELL_HEIGHT_PX) as u64
+}
+
+/// Thumbnail as PNG bytes, for protocols that take an encoded image
+fn thumbnail_png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let thumb = image.thumbnail(PREVIEW_COLS * CELL_PX, PREVIEW_ROWS * CELL_HEIGHT_PX);
+    let mut png = Vec::new();
+    thumb
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode preview")?;
+    Ok(png)
+}
+
+/// Scale to fit `max_width` x `max_height` pixels and composite transparency
+/// onto white, since most logos are drawn for light backgrounds
+fn flatten(image: &DynamicImage, max_width: u32, max_height: u32) -> RgbImage {
+    let (w, h) = image.dimensions();
+    let scale = (max_width as f32 / w.max(1) as f32).min(max_height as f32 / h.max(1) as f32);
+    let width = (w as f32 * scale).round().clamp(1.0, max_width as f32) as u32;
+    let height = (h as f32 * scale).round().clamp(1.0, max_height as f32) as u32;
+    let thumb = image
+        .resize_exact(width, height, image::imageops::FilterType::Triangle)
+        .to_rgba8();
+
+    RgbImage::from_fn(thumb.width(), thumb.height(), |x, y| {
+        let p = thumb.get_pixel(x, y);
+        let a = p[3] as u32;
+        let blend = |c: u8| ((c as u32 * a + 255 * (255 - a)) / 255) as u8;
+        Rgb([blend(p[0]), blend(p[1]), blend(p[2])])
+    })
+}
+
+fn kitty(image: &DynamicImage) -> Result<String> {
+    let data = base64::engine::general_purpose::STANDARD.encode(thumbnail_png(image)?);
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(4096)
+        .map(|c| std::str::from_utf8(c).unwrap_or_default())
+        .collect();
+
+    // Give kitty one dimension only, so it keeps the aspect ratio
+    let size = if is_tall(image) {
+        format!("r={}", PREVIEW_ROWS)
+    } else {
+        format!("c={}", PREVIEW_COLS)
+    };
+
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        if i == 0 {
+            out.push_str(&format!("\x1b_Ga=T,f=100,q=2,{},m={};{}\x1b\\", size, more, chunk));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    Ok(out)
+}
+
+fn iterm(image: &DynamicImage) -> Result<String> {
+    let png = thumbnail_png(image)?;
+    Ok(format!(
+        "\x1b]1337;File=inline=1;width={};height={};preserveAspectRatio=1;size={}:{}\x07",
+        PREVIEW_COLS,
+        PREVIEW_ROWS,
+        png.len(),
+        base64::engine::general_purpose::STANDARD.encode(&png)
+    ))
+}
+
+/// Encode as Sixel using a fixed 6x6x6 colour cube
+fn sixel(image: &RgbImage) -> String {
+    let (w, h) = image.dimensions();
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    let index = |p: &Rgb<u8>| level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]);
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", w, h);
+    for i in 0..216 {
+        out.push_str(&format!("#{};2;{};{};{}", i, i / 36 * 20, i / 6 % 6 * 20, i % 6 * 20));
+    }
+
+    for band in (0..h).step_by(6) {
+        // Bit masks per colour per column for this six-pixel band
+        let mut layers: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for x in 0..w {
+            for dy in 0..6 {
+                let y = band + dy;
+                if y >= h {
+                    break;
+                }
+                let color = index(image.get_pixel(x, y));
+                layers.entry(color).or_insert_with(|| vec![0; w as usize])[x as usize] |= 1 << dy;
+            }
+        }
+
+        for (color, columns) in layers {
+            out.push_str(&format!("#{}", color));
+            let mut x = 0;
+            while x < columns.len() {
+                let run = columns[x..].iter().take_while(|&&c| c == columns[x]).count();
+                let ch = (63 + columns[x]) as char;
+                if run > 3 {
+                    out.push_str(&format!("!{}{}", run, ch));
+                } else {
+                    out.extend(std::iter::repeat_n(ch, run));
+                }
+                x += run;
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Two pixels per cell using the upper half block with separate fg/bg colours
+fn blocks(image: &RgbImage, indent: &str) -> String {
+    let (w, h) = image.dimensions();
+    let mut lines = Vec::new();
+
+    for y in (0..h).step_by(2) {
+        let mut line = String::from(indent);
+        for x in 0..w {
+            let top = image.get_pixel(x, y);
+            let bottom = if y + 1 < h { image.get_pixel(x, y + 1) } else { top };
+            line.push_str(&format!(
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+            ));
+        }
+        line.push_str("\x1b[0m");
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_fits_both_dimensions() {
+        let wide = DynamicImage::new_rgba8(400, 100);
+        assert_eq!(flatten(&wide, PREVIEW_COLS, PREVIEW_ROWS * 2).dimensions(), (24, 6));
+        assert!(!is_tall(&wide));
+
+        let tall = DynamicImage::new_rgba8(100, 2000);
+        assert_eq!(flatten(&tall, PREVIEW_COLS, PREVIEW_ROWS * 2).dimensions(), (1, 24));
+        assert!(is_tall(&tall));
+    }
+}
@@ -0,0 +1,102 @@
+mod serper;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::ai::ExpandedQuery;
+use crate::config::Config;
+
+pub use serper::SerperProvider;
+
+/// Names accepted by `--provider` and `search.provider` in the config file
+pub const PROVIDERS: &[&str] = &["serper"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageResult {
+    pub id: String,
+    pub title: String,
+    pub download_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub source_query: String,
+}
+
+/// Per-query requirements that take precedence over what the query expander chose
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Constraints {
+    pub img_size: Option<String>,
+    pub img_type: Option<String>,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+}
+
+impl Constraints {
+    /// Force the size and type filters onto an expanded query
+    pub fn apply(&self, expanded: &mut ExpandedQuery) {
+        if self.img_size.is_some() {
+            expanded.img_size = self.img_size.clone();
+        }
+        if self.img_type.is_some() {
+            expanded.img_type = self.img_type.clone();
+        }
+    }
+
+    /// Whether the dimensions meet the minimums; unknown (zero) dimensions pass
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        !(self.min_width.is_some_and(|min| width > 0 && width < min)
+            || self.min_height.is_some_and(|min| height > 0 && height < min))
+    }
+}
+
+/// An image search engine that fetchr can query for candidates.
+///
+/// Implementations translate the generic `ExpandedQuery` filters into their own
+/// filter vocabulary and return results in ranked order.
+#[async_trait]
+pub trait ImageSearchProvider: Send + Sync {
+    /// Short name used in config and on the command line
+    fn name(&self) -> &'static str;
+
+    /// Search for up to `limit` images matching the expanded query
+    async fn search_images(
+        &self,
+        expanded: &ExpandedQuery,
+        original_query: &str,
+        limit: usize,
+    ) -> Result<Vec<ImageResult>>;
+}
+
+/// Build the search provider selected by `--provider` or the config file
+pub fn provider_from_config(
+    config: &Config,
+    override_name: Option<&str>,
+) -> Result<Box<dyn ImageSearchProvider>> {
+    let name = override_name
+        .unwrap_or(&config.search.provider)
+        .to_lowercase();
+
+    match name.as_str() {
+        "serper" => {
+            let api_key = config
+                .keys
+                .serper
+                .clone()
+                .context("Serper API key not set. Run: fetchr config set-key serper <KEY>")?;
+            Ok(Box::new(SerperProvider::new(api_key, config.search.base_url.clone())))
+        }
+        _ => anyhow::bail!(
+            "Unknown search provider: {}. Available: {}",
+            name,
+            PROVIDERS.join(", ")
+        ),
+    }
+}
+
+fn simple_hash(input: &str) -> u64 {
+    let mut hash: u64 = 0;
+    for byte in input.bytes() {
+        hash = hash.wrapping_mul(31).wrapping_add(byte as u64);
+    }
+    hash
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{simple_hash, ImageResult, ImageSearchProvider};
+use crate::ai::ExpandedQuery;
+use crate::errors;
+use crate::http::{self, RetryPolicy};
+
+const DEFAULT_BASE_URL: &str = "https://google.serper.dev";
+
+/// Google Images results via serper.dev
+pub struct SerperProvider {
+    api_key: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl SerperProvider {
+    pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            client: http::client().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SerperRequest {
+    q: String,
+    num: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tbs: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SerperResponse {
+    images: Option<Vec<SerperImage>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerperImage {
+    title: String,
+    image_url: String,
+    image_width: Option<u32>,
+    image_height: Option<u32>,
+}
+
+/// Translate fetchr's filter names into Google's `tbs` image parameters
+fn map_filters(img_size: Option<&str>, img_type: Option<&str>) -> Option<String> {
+    let size = match img_size.map(|s| s.to_lowercase()).as_deref() {
+        Some("large") => Some("isz:l"),
+        Some("medium") => Some("isz:m"),
+        Some("icon") => Some("isz:i"),
+        _ => None,
+    };
+    let kind = match img_type.map(|s| s.to_lowercase()).as_deref() {
+        Some("photo") => Some("itp:photo"),
+        Some("clipart") => Some("itp:clipart"),
+        Some("lineart") => Some("itp:lineart"),
+        Some("face") => Some("itp:face"),
+        _ => None,
+    };
+
+    let parts: Vec<&str> = [size, kind].into_iter().flatten().collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(","))
+    }
+}
+
+#[async_trait]
+impl ImageSearchProvider for SerperProvider {
+    fn name(&self) -> &'static str {
+        "serper"
+    }
+
+    async fn search_images(
+        &self,
+        expanded: &ExpandedQuery,
+        original_query: &str,
+        limit: usize,
+    ) -> Result<Vec<ImageResult>> {
+        let request = SerperRequest {
+            q: expanded.query.clone(),
+            num: limit.min(100),
+            tbs: map_filters(expanded.img_size.as_deref(), expanded.img_type.as_deref()),
+        };
+
+        let url = format!("{}/images", self.base_url.trim_end_matches('/'));
+        let response = http::send("Serper", &RetryPolicy::API, || {
+            self.client
+                .post(&url)
+                .header("X-API-KEY", &self.api_key)
+                .header("Content-Type", "application/json")
+                .json(&request)
+        })
+        .await
+        .map_err(|e| errors::network_error("Serper", &e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(errors::api_error("Serper", status, &body).into());
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| errors::network_error("Serper", &e))?;
+        let search_response: SerperResponse =
+            serde_json::from_str(&body).map_err(|e| errors::bad_response("Serper", e))?;
+
+        let mut results: Vec<ImageResult> = Vec::new();
+
+        if let Some(images) = search_response.images {
+            for image in images.into_iter().take(limit) {
+                results.push(ImageResult {
+                    id: format!("{:x}", simple_hash(&image.image_url)),
+                    title: image.title,
+                    download_url: image.image_url,
+                    width: image.image_width.unwrap_or(0),
+                    height: image.image_height.unwrap_or(0),
+                    source_query: original_query.to_string(),
+                });
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_filters_to_tbs() {
+        assert_eq!(map_filters(Some("large"), Some("clipart")).as_deref(), Some("isz:l,itp:clipart"));
+        assert_eq!(map_filters(Some("Icon"), None).as_deref(), Some("isz:i"));
+        assert_eq!(map_filters(None, Some("FACE")).as_deref(), Some("itp:face"));
+        assert_eq!(map_filters(None, None), None);
+        // Filters Google has no equivalent for are dropped
+        assert_eq!(map_filters(Some("xxlarge"), Some("animated")), None);
+    }
+}
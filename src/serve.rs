@@ -0,0 +1,458 @@
+//! `fetchr serve`: a local HTTP API over `Fetcher`, for editor plugins and
+//! design tools that would otherwise spawn the CLI for every request.
+//!
+//! The config is loaded once at startup; the HTTP client and response cache
+//! are shared by every request. Send `Accept: text/event-stream` to `/find`
+//! to follow a job's progress as Server-Sent Events.
+//!
+//! Any web page the user opens can send requests to localhost, so the server
+//! only accepts JSON bodies, refuses other origins and saves nothing outside
+//! the root directory it was started with.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use axum::body::Bytes;
+use axum::extract::{Query, Request, State};
+use axum::http::uri::Authority;
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::stream;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::cache::CacheMode;
+use crate::config::{self, Config};
+use crate::download::{self, ConflictPolicy, DownloadItem};
+use crate::errors::{self, FetchrError};
+use crate::feedback::{self, ApiFeedbackEntry, ApiRating, Rating};
+use crate::fetcher::{Fetcher, FetcherBuilder, FindResult, Pick};
+use crate::input::{self, InputFormat};
+use crate::output::{ChosenImage, ErrorRecord};
+use crate::search::ImageResult;
+
+/// Entries `GET /history` returns when no `limit` is given
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// Options for `fetchr serve`
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub addr: SocketAddr,
+    /// Where images are saved; a request's `output_dir` is relative to it
+    /// (default: `defaults.output_dir`, then Downloads/fetchr)
+    pub root: Option<PathBuf>,
+    /// Defaults for requests that don't set `no_ai` or `provider`
+    pub no_ai: bool,
+    pub provider: Option<String>,
+    pub cache: CacheMode,
+}
+
+struct AppState {
+    config: Config,
+    options: ServeOptions,
+    root: PathBuf,
+    /// The port we're listening on, which `Host` must name
+    port: u16,
+}
+
+/// Serve the API until Ctrl-C
+pub async fn run(options: ServeOptions) -> Result<()> {
+    let config = config::load().map_err(errors::config_error)?;
+    let root = download::resolve_output_dir(options.root.as_deref(), &config).map_err(errors::config_error)?;
+    let listener = tokio::net::TcpListener::bind(options.addr)
+        .await
+        .with_context(|| format!("Failed to listen on {}", options.addr))?;
+    let local_addr = listener.local_addr()?;
+    let state = Arc::new(AppState {
+        config,
+        options,
+        root,
+        port: local_addr.port(),
+    });
+    println!(
+        "  \x1b[32m✓\x1b[0m Listening on \x1b[1mhttp://{}\x1b[0m, saving to {} \x1b[90m(Ctrl-C to stop)\x1b[0m",
+        local_addr,
+        state.root.display()
+    );
+
+    let app = Router::new()
+        .route("/find", post(find))
+        .route("/download", post(download))
+        .route("/history", get(history))
+        .route("/feedback", post(rate))
+        .layer(middleware::from_fn_with_state(state.clone(), guard))
+        .with_state(state);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("Server failed")?;
+    Ok(())
+}
+
+/// An error response: `{"error": {...}}`, in the same shape as `--format json`
+struct ApiError {
+    status: StatusCode,
+    error: anyhow::Error,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, error: anyhow::Error) -> Self {
+        Self { status, error }
+    }
+
+    fn bad_request(error: anyhow::Error) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, error)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        let status = match error.downcast_ref::<FetchrError>() {
+            Some(FetchrError::Auth(_) | FetchrError::Quota(_) | FetchrError::BadResponse(_)) => {
+                StatusCode::BAD_GATEWAY
+            }
+            Some(FetchrError::RateLimited(_) | FetchrError::ServiceDown(_) | FetchrError::Network(_)) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            // An unknown provider and the like come from the request's settings
+            Some(FetchrError::BadRequest(_) | FetchrError::Config { .. }) => StatusCode::BAD_REQUEST,
+            Some(FetchrError::NoResults { .. }) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self { status, error }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: ErrorRecord,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: ErrorRecord::from_error(&self.error),
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Turn away what a web page could send us. A `text/plain` POST needs no CORS
+/// preflight, and a page on a rebound domain is same-origin, so check the body
+/// type, `Origin` and `Host` ourselves.
+async fn guard(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let text = |name| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let host = text(header::HOST).unwrap_or_default();
+    if !state.is_local_host(host) {
+        return ApiError::new(StatusCode::FORBIDDEN, anyhow!("Host {:?} is not this server", host)).into_response();
+    }
+    if let Some(origin) = text(header::ORIGIN) {
+        if origin != format!("http://{}", host) {
+            return ApiError::new(StatusCode::FORBIDDEN, anyhow!("Requests from {} are not allowed", origin))
+                .into_response();
+        }
+    }
+    if request.method() == Method::POST {
+        let mime = text(header::CONTENT_TYPE).and_then(|v| v.split(';').next()).unwrap_or_default();
+        if !mime.trim().eq_ignore_ascii_case("application/json") {
+            return ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                anyhow!("Send the body as Content-Type: application/json"),
+            )
+            .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Parse a JSON body ourselves, so malformed requests get the usual error shape
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice(body)
+        .context("Invalid request body")
+        .map_err(ApiError::bad_request)
+}
+
+/// Settings shared by `/find` and `/download`; unset ones come from the config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JobOptions {
+    count: Option<usize>,
+    auto: bool,
+    max_retries: Option<u32>,
+    no_ai: Option<bool>,
+    provider: Option<String>,
+    /// Relative to the server's root
+    output_dir: Option<PathBuf>,
+    name_template: Option<String>,
+    on_conflict: Option<ConflictPolicy>,
+}
+
+impl AppState {
+    /// `localhost` or an IP address, with our port: never a domain name that
+    /// could be pointed at 127.0.0.1 by someone else's DNS
+    fn is_local_host(&self, host: &str) -> bool {
+        let Ok(authority) = host.parse::<Authority>() else {
+            return false;
+        };
+        let name = authority.host().trim_start_matches('[').trim_end_matches(']');
+        (name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok())
+            && authority.port_u16().unwrap_or(80) == self.port
+    }
+
+    /// Where a job saves: its `output_dir`, if any, must stay inside the root
+    fn output_dir(&self, options: &JobOptions) -> Result<PathBuf, ApiError> {
+        let Some(dir) = &options.output_dir else {
+            return Ok(self.root.clone());
+        };
+        download::confined_dir(&self.root, dir).map_err(ApiError::bad_request)
+    }
+
+    fn fetcher(&self, options: &JobOptions) -> FetcherBuilder {
+        let mut builder = Fetcher::builder()
+            .config(self.config.clone())
+            .cache(self.options.cache)
+            .no_ai(options.no_ai.unwrap_or(self.options.no_ai))
+            .auto(options.auto);
+        if let Some(provider) = options.provider.as_ref().or(self.options.provider.as_ref()) {
+            builder = builder.provider_name(provider);
+        }
+        if let Some(count) = options.count {
+            builder = builder.count(count);
+        }
+        if let Some(max_retries) = options.max_retries {
+            builder = builder.max_retries(max_retries);
+        }
+        if let Some(template) = &options.name_template {
+            builder = builder.name_template(template);
+        }
+        if let Some(policy) = options.on_conflict {
+            builder = builder.on_conflict(policy);
+        }
+        builder
+    }
+}
+
+#[derive(Deserialize)]
+struct FindRequest {
+    /// Query strings ("Tesla logo @3") or objects, as in a JSON batch file
+    queries: Vec<serde_json::Value>,
+    /// Save the picks too, instead of only returning them
+    #[serde(default)]
+    download: bool,
+    #[serde(flatten)]
+    options: JobOptions,
+}
+
+#[derive(Serialize)]
+struct QueryResponse {
+    #[serde(flatten)]
+    result: FindResult,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    chosen: Vec<ChosenImage>,
+}
+
+#[derive(Serialize)]
+struct FindResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dir: Option<PathBuf>,
+    queries: Vec<QueryResponse>,
+}
+
+fn wants_events(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"))
+}
+
+fn sse_event(name: &str, data: &impl Serialize) -> SseEvent {
+    SseEvent::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|_| SseEvent::default().event(name))
+}
+
+async fn find(State(state): State<Arc<AppState>>, headers: HeaderMap, body: Bytes) -> Response {
+    match start_find(state, &headers, &body).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn start_find(state: Arc<AppState>, headers: &HeaderMap, body: &[u8]) -> Result<Response, ApiError> {
+    let request: FindRequest = parse_body(body)?;
+    let queries = serde_json::to_string(&request.queries).map_err(|e| ApiError::bad_request(e.into()))?;
+    let specs = input::parse_queries(&queries, InputFormat::Json).map_err(ApiError::bad_request)?;
+    if specs.is_empty() {
+        return Err(ApiError::bad_request(anyhow::anyhow!("No queries given")));
+    }
+    eprintln!("  POST /find: {} quer{}", specs.len(), if specs.len() == 1 { "y" } else { "ies" });
+
+    let output_dir = request.download.then(|| state.output_dir(&request.options)).transpose()?;
+    let builder = state.fetcher(&request.options);
+    if !wants_events(headers) {
+        let fetcher = builder.build()?;
+        let response = run_find(&fetcher, &specs, output_dir).await?;
+        return Ok(Json(response).into_response());
+    }
+
+    // Progress events until the job ends, then one `result` or `error` event.
+    // The stream closes once the job and its progress callback are dropped.
+    let (tx, rx) = mpsc::unbounded_channel();
+    let progress = tx.clone();
+    let fetcher = builder
+        .on_progress(move |p| {
+            let _ = progress.send(sse_event("progress", p));
+        })
+        .build()?;
+    tokio::spawn(async move {
+        let event = match run_find(&fetcher, &specs, output_dir).await {
+            Ok(response) => sse_event("result", &response),
+            Err(e) => sse_event("error", &ErrorRecord::from_error(&e)),
+        };
+        let _ = tx.send(event);
+    });
+
+    let events = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok::<_, Infallible>(event), rx))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response())
+}
+
+/// Find images for `specs`, saving the picks too when given an `output_dir`
+async fn run_find(fetcher: &Fetcher, specs: &[input::QuerySpec], output_dir: Option<PathBuf>) -> Result<FindResponse> {
+    let results = fetcher.find_many(specs).await;
+    let Some(output_dir) = output_dir else {
+        return Ok(FindResponse {
+            output_dir: None,
+            queries: results
+                .into_iter()
+                .map(|result| QueryResponse {
+                    result,
+                    chosen: Vec::new(),
+                })
+                .collect(),
+        });
+    };
+
+    let items: Vec<DownloadItem> = results.iter().flat_map(|r| r.download_items()).collect();
+    let mut chosen = fetcher.download_to(&items, &output_dir).await?.into_iter();
+    let queries = results
+        .into_iter()
+        .map(|result| {
+            let chosen = chosen.by_ref().take(result.picks.len()).collect();
+            QueryResponse { result, chosen }
+        })
+        .collect();
+    Ok(FindResponse {
+        output_dir: Some(output_dir),
+        queries,
+    })
+}
+
+#[derive(Deserialize)]
+struct DownloadRequest {
+    /// Search results or picks from `/find`
+    images: Vec<ImageResult>,
+    #[serde(flatten)]
+    options: JobOptions,
+}
+
+#[derive(Serialize)]
+struct DownloadResponse {
+    output_dir: PathBuf,
+    images: Vec<ChosenImage>,
+}
+
+async fn download(State(state): State<Arc<AppState>>, body: Bytes) -> Result<Json<DownloadResponse>, ApiError> {
+    let request: DownloadRequest = parse_body(&body)?;
+    eprintln!("  POST /download: {} image{}", request.images.len(), if request.images.len() == 1 { "" } else { "s" });
+
+    // Number images per query, as `find` would have, for {n} in the template
+    let mut picks: HashMap<&str, usize> = HashMap::new();
+    for image in &request.images {
+        *picks.entry(image.source_query.as_str()).or_default() += 1;
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let items: Vec<DownloadItem> = request
+        .images
+        .iter()
+        .map(|image| {
+            let pick = seen.entry(image.source_query.as_str()).or_default();
+            *pick += 1;
+            DownloadItem {
+                image: image.clone(),
+                pick: *pick,
+                picks: picks[image.source_query.as_str()],
+                name_template: None,
+                fallbacks: Vec::new(),
+                index: None,
+            }
+        })
+        .collect();
+
+    let output_dir = state.output_dir(&request.options)?;
+    let fetcher = state.fetcher(&request.options).build()?;
+    let images = fetcher.download_to(&items, &output_dir).await?;
+    Ok(Json(DownloadResponse { output_dir, images }))
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct HistoryResponse {
+    thumbs_up: usize,
+    thumbs_down: usize,
+    skipped: usize,
+    /// Most recent first
+    entries: Vec<ApiFeedbackEntry>,
+}
+
+async fn history(Query(query): Query<HistoryQuery>) -> Result<Json<HistoryResponse>, ApiError> {
+    let history = feedback::load_history()?;
+    let count = |rating| history.entries.iter().filter(|e| e.rating == rating).count();
+    Ok(Json(HistoryResponse {
+        thumbs_up: count(Rating::ThumbsUp),
+        thumbs_down: count(Rating::ThumbsDown),
+        skipped: count(Rating::Skip),
+        entries: history
+            .entries
+            .iter()
+            .rev()
+            .take(query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT))
+            .cloned()
+            .map(ApiFeedbackEntry::from)
+            .collect(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct FeedbackRequest {
+    /// A pick from `/find`, with the expanded query that found it
+    image: Pick,
+    rating: ApiRating,
+}
+
+async fn rate(body: Bytes) -> Result<(StatusCode, Json<ApiFeedbackEntry>), ApiError> {
+    let request: FeedbackRequest = parse_body(&body)?;
+    let entry = request.image.feedback(request.rating.into(), false);
+    feedback::append_entry(entry.clone())?;
+    Ok((StatusCode::CREATED, Json(entry.into())))
+}
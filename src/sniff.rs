@@ -0,0 +1,208 @@
+use anyhow::Result;
+
+/// Image formats fetchr knows how to recognize
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    WebP,
+    Svg,
+    Avif,
+    Ico,
+    Bmp,
+    Tiff,
+}
+
+impl ImageFormat {
+    /// File extension used when saving
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Svg => "svg",
+            ImageFormat::Avif => "avif",
+            ImageFormat::Ico => "ico",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Tiff => "tiff",
+        }
+    }
+
+    /// Map a `Content-Type` header value to a format
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        match mime.as_str() {
+            "image/png" | "image/apng" => Some(ImageFormat::Png),
+            "image/jpeg" | "image/jpg" | "image/pjpeg" => Some(ImageFormat::Jpeg),
+            "image/gif" => Some(ImageFormat::Gif),
+            "image/webp" => Some(ImageFormat::WebP),
+            "image/svg+xml" => Some(ImageFormat::Svg),
+            "image/avif" => Some(ImageFormat::Avif),
+            "image/x-icon" | "image/vnd.microsoft.icon" => Some(ImageFormat::Ico),
+            "image/bmp" | "image/x-ms-bmp" => Some(ImageFormat::Bmp),
+            "image/tiff" => Some(ImageFormat::Tiff),
+            _ => None,
+        }
+    }
+
+    /// Recognize a format from the first bytes of a file
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(ImageFormat::WebP)
+        } else if bytes.len() >= 12
+            && &bytes[4..8] == b"ftyp"
+            && matches!(&bytes[8..12], b"avif" | b"avis")
+        {
+            Some(ImageFormat::Avif)
+        } else if bytes.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+            Some(ImageFormat::Ico)
+        } else if is_bmp(bytes) {
+            Some(ImageFormat::Bmp)
+        } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+            Some(ImageFormat::Tiff)
+        } else if looks_like_svg(bytes) {
+            Some(ImageFormat::Svg)
+        } else {
+            None
+        }
+    }
+}
+
+/// "BM" plus a known DIB header size; "BM" alone is too common a text prefix
+fn is_bmp(bytes: &[u8]) -> bool {
+    if bytes.len() < 18 || !bytes.starts_with(b"BM") {
+        return false;
+    }
+    let dib_header = u32::from_le_bytes([bytes[14], bytes[15], bytes[16], bytes[17]]);
+    matches!(dib_header, 12 | 40 | 52 | 56 | 64 | 108 | 124)
+}
+
+/// SVG has no magic number; the first element in the first kilobyte, after
+/// any XML declaration, comments and doctype, must be `<svg`. An HTML page
+/// with an inline icon starts with `<html` instead.
+fn looks_like_svg(bytes: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_lowercase();
+    let mut rest = head.trim_start_matches('\u{feff}').trim_start();
+    loop {
+        let end = if rest.starts_with("<?") {
+            rest.find("?>").map(|i| i + 2)
+        } else if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3)
+        } else if rest.starts_with("<!doctype") {
+            // The internal subset may hold `>`s of its own
+            match (rest.find('['), rest.find('>')) {
+                (Some(open), Some(close)) if open < close => rest.find("]>").map(|i| i + 2),
+                (_, close) => close.map(|i| i + 1),
+            }
+        } else {
+            break;
+        };
+        match end {
+            Some(end) => rest = rest[end..].trim_start(),
+            None => return false,
+        }
+    }
+    rest.strip_prefix("<svg")
+        .and_then(|after| after.chars().next())
+        .is_some_and(|c| c.is_whitespace() || c == '>' || c == '/')
+}
+
+/// Number of leading bytes `detect` needs to recognize every format
+pub const SNIFF_LEN: usize = 1024;
+
+/// Work out the real format of a payload, preferring magic bytes over the
+/// server's `Content-Type`. Fails for HTML error pages and other non-images.
+pub fn detect(content_type: Option<&str>, bytes: &[u8]) -> Result<ImageFormat> {
+    if let Some(format) = ImageFormat::from_magic(bytes) {
+        return Ok(format);
+    }
+
+    // Unknown magic: trust an image Content-Type unless the body is obviously markup
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(256)]).to_lowercase();
+    let is_markup = head.trim_start().starts_with('<');
+    if let Some(format) = content_type.and_then(ImageFormat::from_content_type) {
+        if !is_markup {
+            return Ok(format);
+        }
+    }
+
+    anyhow::bail!(
+        "Not an image (Content-Type: {})",
+        content_type.unwrap_or("unknown")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bmp(dib_header: u32) -> Vec<u8> {
+        let mut bytes = b"BM".to_vec();
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.extend_from_slice(&dib_header.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn recognizes_magic_bytes() {
+        assert_eq!(ImageFormat::from_magic(b"\x89PNG\r\n\x1a\n...."), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_magic(&[0xFF, 0xD8, 0xFF, 0xE0]), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_magic(b"GIF89a..."), Some(ImageFormat::Gif));
+        assert_eq!(ImageFormat::from_magic(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageFormat::WebP));
+        assert_eq!(ImageFormat::from_magic(b"\0\0\0\x1cftypavif"), Some(ImageFormat::Avif));
+        assert_eq!(ImageFormat::from_magic(b"II*\0...."), Some(ImageFormat::Tiff));
+    }
+
+    #[test]
+    fn bmp_needs_a_known_dib_header() {
+        assert_eq!(ImageFormat::from_magic(&bmp(40)), Some(ImageFormat::Bmp));
+        assert_eq!(ImageFormat::from_magic(&bmp(124)), Some(ImageFormat::Bmp));
+        assert_eq!(ImageFormat::from_magic(&bmp(7)), None);
+        assert_eq!(ImageFormat::from_magic(b"BM"), None);
+        assert_eq!(ImageFormat::from_magic(b"BMW owners club, est. 1978"), None);
+    }
+
+    #[test]
+    fn svg_after_prolog_comments_and_doctype() {
+        let svg = concat!(
+            "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<!-- Generator: Adobe Illustrator -->\n",
+            "<!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" [\n  <!ENTITY ns \"http://www.w3.org/2000/svg\">\n]>\n",
+            "<svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+        );
+        assert_eq!(ImageFormat::from_magic(svg.as_bytes()), Some(ImageFormat::Svg));
+        assert_eq!(ImageFormat::from_magic(b"<SVG>"), Some(ImageFormat::Svg));
+        assert_eq!(ImageFormat::from_magic(b"<svg/>"), Some(ImageFormat::Svg));
+    }
+
+    #[test]
+    fn html_with_inline_svg_is_not_svg() {
+        let page = "<!-- <svg> --><!DOCTYPE html><html><body><svg viewBox=\"0 0 1 1\"></svg></body></html>";
+        assert_eq!(ImageFormat::from_magic(page.as_bytes()), None);
+        assert_eq!(ImageFormat::from_magic(b"<svgfoo>"), None);
+        assert_eq!(ImageFormat::from_magic(b"<!-- never closed <svg>"), None);
+    }
+
+    #[test]
+    fn detect_refuses_markup_behind_an_image_content_type() {
+        let html = b"<!DOCTYPE html><html><title>404</title></html>";
+        assert!(detect(Some("image/png"), html).is_err());
+        assert!(detect(None, b"plain text").is_err());
+        assert_eq!(detect(Some("image/x-icon; charset=binary"), b"\x01\x02").unwrap(), ImageFormat::Ico);
+        assert_eq!(detect(Some("text/html"), b"GIF87a").unwrap(), ImageFormat::Gif);
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use reqwest::{Request, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Query parameters and headers that carry credentials
+const SECRET_PARAMS: &[&str] = &["key", "api_key", "apikey", "token", "access_token"];
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "x-api-key",
+    "x-goog-api-key",
+    "api-key",
+    "cookie",
+];
+/// Response headers worth keeping; the rest (dates, cookies, tracing ids) is noise
+const KEPT_RESPONSE_HEADERS: &[&str] = &["content-type", "retry-after"];
+const REDACTED: &str = "REDACTED";
+
+/// One request and the response it got, stored one per file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    /// With credentials redacted
+    url: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<serde_json::Value>,
+    /// Tells apart requests to the same URL; hand-written fixtures can leave it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    /// Text bodies; JSON is stored as JSON so fixtures stay readable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<serde_json::Value>,
+    /// Binary bodies such as images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+enum Mode {
+    /// Send requests for real and save each exchange
+    Record { next: AtomicUsize },
+    /// Answer from saved exchanges; the flag marks ones already served
+    Replay { exchanges: Mutex<Vec<(Exchange, bool)>> },
+}
+
+/// Captures HTTP traffic to a directory (`--record`) or serves it back (`--replay`)
+pub struct Tape {
+    dir: PathBuf,
+    mode: Mode,
+}
+
+static TAPE: OnceLock<Tape> = OnceLock::new();
+
+/// The tape for this run, if `--record` or `--replay` was given
+pub fn active() -> Option<&'static Tape> {
+    TAPE.get()
+}
+
+/// Save every HTTP exchange in this run to `dir`
+pub fn record(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    // Continue numbering after earlier recordings in the same directory
+    let next = load(dir)?.len();
+    install(Tape {
+        dir: dir.to_path_buf(),
+        mode: Mode::Record {
+            next: AtomicUsize::new(next),
+        },
+    })
+}
+
+/// Answer every HTTP request in this run from the exchanges saved in `dir`
+pub fn replay(dir: &Path) -> Result<()> {
+    let exchanges = load(dir)?;
+    if exchanges.is_empty() {
+        anyhow::bail!("No recorded exchanges in {:?}", dir);
+    }
+    install(Tape {
+        dir: dir.to_path_buf(),
+        mode: Mode::Replay {
+            exchanges: Mutex::new(exchanges.into_iter().map(|e| (e, false)).collect()),
+        },
+    })
+}
+
+fn install(tape: Tape) -> Result<()> {
+    TAPE.set(tape)
+        .map_err(|_| anyhow::anyhow!("--record and --replay can only be set once"))
+}
+
+/// Exchanges in `dir`, in file name (i.e. recording) order
+fn load(dir: &Path) -> Result<Vec<Exchange>> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+    };
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {:?}", path))?;
+            serde_json::from_str(&content).with_context(|| format!("Invalid exchange in {:?}", path))
+        })
+        .collect()
+}
+
+impl Tape {
+    pub async fn execute(
+        &self,
+        client: &reqwest::Client,
+        request: Request,
+    ) -> Result<Response, reqwest::Error> {
+        let recorded = record_request(&request);
+        match &self.mode {
+            Mode::Record { next } => {
+                let response = client.execute(request).await?;
+                let status = response.status().as_u16();
+                let headers = kept_headers(&response);
+                let bytes = response.bytes().await?;
+
+                let exchange = Exchange {
+                    request: recorded,
+                    response: record_body(status, headers, &bytes),
+                };
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if let Err(e) = self.save(index, &exchange) {
+                    eprintln!("  \x1b[33m!\x1b[0m Failed to record exchange: {:#}", e);
+                }
+                Ok(build_response(&exchange.response))
+            }
+            Mode::Replay { exchanges } => {
+                let found = {
+                    let mut exchanges = exchanges.lock().unwrap();
+                    find(&mut exchanges, &recorded)
+                };
+                match found {
+                    Some(response) => Ok(build_response(&response)),
+                    None => {
+                        eprintln!(
+                            "  \x1b[33m!\x1b[0m No recorded response for {} {} in {:?}",
+                            recorded.method, recorded.url, self.dir
+                        );
+                        Ok(missing_response(&recorded))
+                    }
+                }
+            }
+        }
+    }
+
+    fn save(&self, index: usize, exchange: &Exchange) -> Result<()> {
+        let host = reqwest::Url::parse(&exchange.request.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string());
+        let name = format!(
+            "{:03}-{}-{}.json",
+            index,
+            exchange.request.method.to_lowercase(),
+            host.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "_")
+        );
+        let json = serde_json::to_string_pretty(exchange)?;
+        fs::write(self.dir.join(name), json + "\n")?;
+        Ok(())
+    }
+}
+
+/// Pick the response for `request`: an unused exchange with the same method, URL
+/// and body first, then any unused one with the same method and URL, and finally
+/// the last one served, for requests repeated more often than recorded
+fn find(exchanges: &mut [(Exchange, bool)], request: &RecordedRequest) -> Option<RecordedResponse> {
+    let same_target = |e: &Exchange| e.request.method == request.method && e.request.url == request.url;
+    let same_body = |e: &Exchange| {
+        e.request.body_sha256.is_none() || e.request.body_sha256 == request.body_sha256
+    };
+
+    let index = exchanges
+        .iter()
+        .position(|(e, used)| !used && same_target(e) && same_body(e))
+        .or_else(|| exchanges.iter().position(|(e, used)| !used && same_target(e)))
+        .or_else(|| exchanges.iter().rposition(|(e, _)| same_target(e)))?;
+
+    exchanges[index].1 = true;
+    Some(exchanges[index].0.response.clone())
+}
+
+fn record_request(request: &Request) -> RecordedRequest {
+    let headers = request
+        .headers()
+        .iter()
+        .filter(|(name, _)| *name != reqwest::header::USER_AGENT)
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                value.to_str().unwrap_or_default().to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect();
+
+    let bytes = request.body().and_then(|b| b.as_bytes());
+    RecordedRequest {
+        method: request.method().to_string(),
+        url: redact_url(request.url()),
+        headers,
+        body: bytes.map(|b| {
+            serde_json::from_slice(b)
+                .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(b).into_owned()))
+        }),
+        body_sha256: bytes.map(|b| format!("{:x}", Sha256::digest(b))),
+    }
+}
+
+fn redact_url(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if SECRET_PARAMS.contains(&name.as_ref()) {
+                    REDACTED.to_string()
+                } else {
+                    value.into_owned()
+                };
+                (name.into_owned(), value)
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+fn kept_headers(response: &Response) -> BTreeMap<String, String> {
+    KEPT_RESPONSE_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = response.headers().get(*name)?.to_str().ok()?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn record_body(status: u16, headers: BTreeMap<String, String>, bytes: &[u8]) -> RecordedResponse {
+    let (body, body_base64) = match std::str::from_utf8(bytes) {
+        Ok(text) => (
+            Some(
+                serde_json::from_str(text)
+                    .unwrap_or_else(|_| serde_json::Value::String(text.to_string())),
+            ),
+            None,
+        ),
+        Err(_) => (
+            None,
+            Some(base64::engine::general_purpose::STANDARD.encode(bytes)),
+        ),
+    };
+    RecordedResponse {
+        status,
+        headers,
+        body,
+        body_base64,
+    }
+}
+
+fn build_response(recorded: &RecordedResponse) -> Response {
+    let bytes = match (&recorded.body, &recorded.body_base64) {
+        (_, Some(encoded)) => base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .unwrap_or_default(),
+        (Some(serde_json::Value::String(text)), None) => text.clone().into_bytes(),
+        (Some(json), None) => json.to_string().into_bytes(),
+        (None, None) => Vec::new(),
+    };
+
+    let mut response = ::http::Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        response = response.header(name, value);
+    }
+    response
+        .body(bytes)
+        .map(Response::from)
+        .unwrap_or_else(|_| Response::from(::http::Response::new(Vec::new())))
+}
+
+/// Stand-in for a request the tape has no answer for; 501 isn't retried, so
+/// the run fails fast with the URL in the error detail
+fn missing_response(request: &RecordedRequest) -> Response {
+    build_response(&RecordedResponse {
+        status: 501,
+        headers: BTreeMap::from([("content-type".to_string(), "application/json".to_string())]),
+        body: Some(serde_json::json!({
+            "error": {
+                "message": format!("No recorded response for {} {}", request.method, request.url)
+            }
+        })),
+        body_base64: None,
+    })
+}
@@ -0,0 +1,82 @@
+//! Helpers shared by the integration tests: a sandboxed `fetchr` and fixtures.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use serde_json::Value;
+use tempfile::TempDir;
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// A scratch home for one run: config, cache, history and downloads
+pub struct Sandbox {
+    pub dir: TempDir,
+}
+
+impl Sandbox {
+    pub fn new(config: &str) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("config.toml"), config).unwrap();
+        Self { dir }
+    }
+
+    /// Keys are required but never leave the machine when replaying
+    pub fn with_keys() -> Self {
+        Self::new("[keys]\ngemini = \"test-gemini-key\"\nserper = \"test-serper-key\"\n")
+    }
+
+    pub fn output_dir(&self) -> PathBuf {
+        self.dir.path().join("out")
+    }
+
+    /// The `fetchr` binary, with no stdin and everything it reads or writes
+    /// inside the sandbox
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_fetchr"));
+        command
+            // Config, history and cache; also keeps project configs out of reach
+            .env("FETCHR_HOME", self.dir.path())
+            .current_dir(self.dir.path())
+            .env_remove("GEMINI_API_KEY")
+            .env_remove("SERPER_API_KEY")
+            .stdin(Stdio::null());
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command()
+            // Before `args`, which end with the query words
+            .arg("-o")
+            .arg(self.output_dir())
+            .args(args)
+            .output()
+            .unwrap()
+    }
+}
+
+pub fn fixtures(scenario: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(scenario)
+        .display()
+        .to_string()
+}
+
+pub fn json_output(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+        panic!(
+            "stdout is not JSON ({}):\n{}\nstderr:\n{}",
+            e,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )
+    })
+}
+
+pub fn assert_png(path: &Value) {
+    let bytes = fs::read(path.as_str().expect("saved image has a path")).unwrap();
+    assert!(bytes.starts_with(PNG_MAGIC), "saved file is not a PNG");
+}
@@ -0,0 +1,90 @@
+//! `fetchr mcp` against recorded API traffic, driven over stdio like an MCP client.
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::Stdio;
+
+use serde_json::Value;
+
+use common::{assert_png, fixtures, Sandbox};
+
+#[test]
+fn mcp_searches_downloads_and_rates() {
+    let sandbox = Sandbox::with_keys();
+    let mut server = sandbox
+        .command()
+        .args(["--replay", &fixtures("find"), "mcp"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = server.stdin.take().unwrap();
+    let mut replies = BufReader::new(server.stdout.take().unwrap()).lines();
+    let mut call = |id: u64, method: &str, params: Value| -> Value {
+        let request = serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        writeln!(stdin, "{}", request).unwrap();
+        let reply: Value = serde_json::from_str(&replies.next().unwrap().unwrap()).unwrap();
+        assert_eq!(reply["id"], id);
+        reply
+    };
+
+    let init = call(1, "initialize", serde_json::json!({"protocolVersion": "2025-06-18", "capabilities": {}}));
+    assert_eq!(init["result"]["protocolVersion"], "2025-06-18");
+    let tools = call(2, "tools/list", serde_json::json!({}));
+    let names: Vec<&str> = tools["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["search_images", "download_image", "rate_result"]);
+
+    let search = call(
+        3,
+        "tools/call",
+        serde_json::json!({"name": "search_images", "arguments": {"query": "Tesla logo"}}),
+    );
+    assert_eq!(search["result"]["isError"], false);
+    let image = search["result"]["structuredContent"]["images"][0].clone();
+    assert_eq!(image["width"], 512);
+
+    let download = call(
+        4,
+        "tools/call",
+        serde_json::json!({"name": "download_image", "arguments": {"image": image, "output_dir": "out"}}),
+    );
+    let saved = &download["result"]["structuredContent"];
+    assert_eq!(saved["status"], "saved", "{}", download);
+    assert_eq!(
+        saved["download_url"],
+        "https://images.example.com/tesla-logo-official-transparent.png"
+    );
+    assert_eq!(saved["height"], 512);
+    assert_png(&saved["path"]);
+    assert!(Path::new(saved["path"].as_str().unwrap()).parent().unwrap().ends_with("out"));
+
+    // Only folders inside the working directory
+    let escape = call(
+        5,
+        "tools/call",
+        serde_json::json!({"name": "download_image", "arguments": {"image": image, "output_dir": "../escape"}}),
+    );
+    assert_eq!(escape["result"]["isError"], true, "{}", escape);
+
+    let rate = call(
+        6,
+        "tools/call",
+        serde_json::json!({"name": "rate_result", "arguments": {"image": image, "rating": "thumbs_up"}}),
+    );
+    assert_eq!(rate["result"]["isError"], false, "{}", rate);
+
+    let unknown = call(7, "tools/call", serde_json::json!({"name": "nope", "arguments": {}}));
+    assert_eq!(unknown["error"]["code"], -32602);
+
+    // Closing stdin ends the session
+    drop(stdin);
+    assert!(server.wait().unwrap().success());
+}
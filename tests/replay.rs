@@ -0,0 +1,254 @@
+//! End-to-end runs of the `fetchr` binary against recorded API traffic.
+//!
+//! Fixtures in `tests/fixtures/<scenario>/` are `--record` output: one JSON file
+//! per HTTP exchange, with API keys redacted. Re-record a scenario against the
+//! real services with e.g.
+//! `fetchr --record tests/fixtures/find --format json -o /tmp/out "Tesla logo"`.
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{fs, thread};
+
+use base64::Engine;
+use serde_json::Value;
+
+use common::{assert_png, fixtures, json_output, Sandbox};
+
+#[test]
+fn find_downloads_the_top_result() {
+    let sandbox = Sandbox::with_keys();
+    let output = sandbox.run(&["--replay", &fixtures("find"), "--format", "json", "Tesla logo"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let run = json_output(&output);
+    let query = &run["queries"][0];
+    assert_eq!(query["query"], "Tesla logo");
+    assert_eq!(query["expanded"]["query"], "Tesla logo official transparent");
+    assert_eq!(query["expanded"]["img_type"], "clipart");
+    assert_eq!(query["candidates"].as_array().unwrap().len(), 1);
+
+    let chosen = &query["chosen"][0];
+    assert_eq!(chosen["status"], "saved");
+    assert_eq!(
+        chosen["download_url"],
+        "https://images.example.com/tesla-logo-official-transparent.png"
+    );
+    assert_png(&chosen["path"]);
+}
+
+#[test]
+fn auto_reformulates_after_no_results() {
+    let sandbox = Sandbox::with_keys();
+    let output = sandbox.run(&[
+        "--replay",
+        &fixtures("auto"),
+        "--auto",
+        "--format",
+        "json",
+        "Nike swoosh",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let run = json_output(&output);
+    let query = &run["queries"][0];
+    let actions: Vec<&str> = query["decisions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["attempt 1", "no results", "attempt 2", "found"]);
+    assert_eq!(query["expanded"]["query"], "Nike swoosh logo vector");
+    assert_eq!(query["chosen"][0]["status"], "saved");
+    assert_png(&query["chosen"][0]["path"]);
+}
+
+#[test]
+fn auth_error_fails_the_query() {
+    let sandbox = Sandbox::with_keys();
+    let output = sandbox.run(&[
+        "--replay",
+        &fixtures("auth"),
+        "--no-ai",
+        "--format",
+        "json",
+        "Unauthorized logo",
+    ]);
+    // Every query failed
+    assert_eq!(output.status.code(), Some(4));
+
+    let run = json_output(&output);
+    let error = &run["queries"][0]["error"];
+    assert_eq!(error["kind"], "auth");
+    assert_eq!(error["service"], "Serper");
+    assert_eq!(error["status"], 401);
+}
+
+#[test]
+fn unrecorded_request_fails_without_network() {
+    let sandbox = Sandbox::with_keys();
+    // The auth fixture only has a Serper exchange, so the Gemini call has no answer
+    let output = sandbox.run(&["--replay", &fixtures("auth"), "--format", "json", "Tesla logo"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No recorded response for POST https://generativelanguage.googleapis.com"),
+        "stderr:\n{}",
+        stderr
+    );
+}
+
+/// Answers Gemini, Serper and image requests like the real services would, on
+/// a local port, so recording can be tested without network access. Returns the
+/// base URL and a count of requests served.
+fn spawn_mock_server() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let image_url = format!("{}/img/logo.png", base);
+    let flaky_url = format!("{}/img/flaky.png", base);
+    let png = fixture_image();
+    let served = Arc::new(AtomicUsize::new(0));
+    let counter = served.clone();
+    let mut flaky_requests = 0;
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let (content_type, body) = if path.contains(":generateContent") {
+                let text = r#"{"query": "Tesla logo official", "img_size": "large", "img_type": "clipart"}"#;
+                let reply = serde_json::json!({
+                    "candidates": [{"content": {"parts": [{"text": text}]}}]
+                });
+                ("application/json", reply.to_string().into_bytes())
+            } else if path == "/images" {
+                let logo = serde_json::json!({
+                    "title": "Tesla logo",
+                    "imageUrl": image_url,
+                    "imageWidth": 512,
+                    "imageHeight": 512
+                });
+                // Searches for "Flaky ..." rank an image first that passes the
+                // probe but then serves an error page
+                let images = if String::from_utf8_lossy(&body).contains("Flaky") {
+                    let flaky = serde_json::json!({
+                        "title": "Flaky logo",
+                        "imageUrl": flaky_url,
+                        "imageWidth": 512,
+                        "imageHeight": 512
+                    });
+                    vec![flaky, logo]
+                } else {
+                    vec![logo]
+                };
+                let reply = serde_json::json!({ "images": images });
+                ("application/json", reply.to_string().into_bytes())
+            } else if path == "/img/flaky.png" {
+                flaky_requests += 1;
+                if flaky_requests == 1 {
+                    ("image/png", png.clone())
+                } else {
+                    ("text/html", b"<html><body>Hotlinking not allowed</body></html>".to_vec())
+                }
+            } else {
+                ("image/png", png.clone())
+            };
+
+            counter.fetch_add(1, Ordering::SeqCst);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        }
+    });
+
+    (base, served)
+}
+
+/// The PNG served in the `find` fixture
+fn fixture_image() -> Vec<u8> {
+    let path = Path::new(&fixtures("find")).join("002-get-images.example.com.json");
+    let exchange: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    let encoded = exchange["response"]["body_base64"].as_str().unwrap();
+    base64::engine::general_purpose::STANDARD.decode(encoded).unwrap()
+}
+
+#[test]
+fn record_redacts_keys_and_replays() {
+    let (base, served) = spawn_mock_server();
+    let sandbox = Sandbox::new(&format!(
+        "[keys]\ngemini = \"secret-gemini-key\"\nserper = \"secret-serper-key\"\n\n\
+         [ai]\nprovider = \"gemini\"\nbase_url = \"{base}\"\n\n\
+         [search]\nprovider = \"serper\"\nbase_url = \"{base}\"\n"
+    ));
+    let tape = sandbox.dir.path().join("tape");
+    let tape_arg = tape.display().to_string();
+
+    let recorded = sandbox.run(&["--record", &tape_arg, "--format", "json", "Tesla logo"]);
+    assert_eq!(recorded.status.code(), Some(0));
+    let recorded = json_output(&recorded);
+    assert_eq!(recorded["queries"][0]["chosen"][0]["status"], "saved");
+
+    let files: Vec<PathBuf> = fs::read_dir(&tape)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    // Expansion, search, size probe and download
+    assert_eq!(files.len(), 4);
+    for file in &files {
+        let content = fs::read_to_string(file).unwrap();
+        assert!(!content.contains("secret"), "{:?} leaks an API key", file);
+    }
+
+    let requests = served.load(Ordering::SeqCst);
+    fs::remove_dir_all(sandbox.output_dir()).unwrap();
+    let replayed = sandbox.run(&["--replay", &tape_arg, "--format", "json", "Tesla logo"]);
+    assert_eq!(replayed.status.code(), Some(0));
+    assert_eq!(json_output(&replayed), recorded);
+    assert_eq!(served.load(Ordering::SeqCst), requests, "replay went to the network");
+}
+
+#[test]
+fn download_falls_back_when_pick_is_not_an_image() {
+    let (base, _) = spawn_mock_server();
+    let sandbox = Sandbox::new(&format!(
+        "[keys]\nserper = \"test-serper-key\"\n\n[search]\nprovider = \"serper\"\nbase_url = \"{base}\"\n"
+    ));
+    let output = sandbox.run(&["--no-ai", "--format", "json", "Flaky logo"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let run = json_output(&output);
+    let chosen = &run["queries"][0]["chosen"][0];
+    assert_eq!(chosen["status"], "saved");
+    assert_eq!(chosen["download_url"], format!("{}/img/logo.png", base));
+    assert_png(&chosen["path"]);
+}
@@ -0,0 +1,108 @@
+//! `fetchr serve` against recorded API traffic, over plain HTTP/1.1.
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::Stdio;
+
+use serde_json::Value;
+
+use common::{assert_png, fixtures, Sandbox};
+
+/// Send one request with `Connection: close` and return the status and body
+fn http_request(addr: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+    http_request_with(addr, method, path, &["Content-Type: application/json"], body)
+}
+
+fn http_request_with(addr: &str, method: &str, path: &str, headers: &[&str], body: &str) -> (u16, String) {
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let mut headers: String = headers.iter().map(|h| format!("{h}\r\n")).collect();
+    if !headers.starts_with("Host:") {
+        headers.insert_str(0, &format!("Host: {addr}\r\n"));
+    }
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\n{headers}\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+#[test]
+fn serve_finds_downloads_and_rates() {
+    let sandbox = Sandbox::with_keys();
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut server = sandbox
+        .command()
+        .args(["--replay", &fixtures("find"), "serve", "--port", &port.to_string(), "--root"])
+        .arg(sandbox.output_dir())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Wait until it's listening
+    let mut banner = String::new();
+    BufReader::new(server.stdout.take().unwrap())
+        .read_line(&mut banner)
+        .unwrap();
+    assert!(banner.contains("Listening"), "unexpected banner: {}", banner);
+    let addr = format!("127.0.0.1:{}", port);
+
+    let find = serde_json::json!({
+        "queries": ["Tesla logo"],
+        "download": true,
+        "output_dir": "logos",
+    });
+    let (status, body) = http_request(&addr, "POST", "/find", &find.to_string());
+    assert_eq!(status, 200, "{}", body);
+    let found: Value = serde_json::from_str(&body).unwrap();
+    let query = &found["queries"][0];
+    assert_eq!(query["expanded"]["query"], "Tesla logo official transparent");
+    assert_eq!(query["chosen"][0]["status"], "saved");
+    assert_png(&query["chosen"][0]["path"]);
+    assert!(query["chosen"][0]["path"].as_str().unwrap().contains("logos"));
+
+    let rating = serde_json::json!({"image": query["picks"][0], "rating": "thumbs_up"});
+    let (status, body) = http_request(&addr, "POST", "/feedback", &rating.to_string());
+    assert_eq!(status, 201, "{}", body);
+
+    let (status, body) = http_request(&addr, "GET", "/history", "");
+    assert_eq!(status, 200, "{}", body);
+    let history: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(history["thumbs_up"], 1);
+    assert_eq!(history["entries"][0]["expanded_query"], "Tesla logo official transparent");
+
+    let (status, body) = http_request(&addr, "POST", "/find", "{\"queries\": ");
+    assert_eq!(status, 400);
+    assert!(body.contains("Invalid request body"), "{}", body);
+
+    let unknown = serde_json::json!({"queries": ["Tesla logo"], "provider": "nope"});
+    let (status, body) = http_request(&addr, "POST", "/find", &unknown.to_string());
+    assert_eq!(status, 400, "{}", body);
+    assert!(body.contains("nope"), "{}", body);
+
+    // What a web page could send: no preflight, another origin, paths outside the root
+    let plain = ["Content-Type: text/plain"];
+    let (status, _) = http_request_with(&addr, "POST", "/find", &plain, &find.to_string());
+    assert_eq!(status, 415);
+    let foreign = ["Content-Type: application/json", "Origin: https://example.com"];
+    let (status, _) = http_request_with(&addr, "POST", "/find", &foreign, &find.to_string());
+    assert_eq!(status, 403);
+    let (status, _) = http_request_with(&addr, "GET", "/history", &["Host: rebound.example.com"], "");
+    assert_eq!(status, 403);
+    for dir in ["/tmp", "../escape"] {
+        let escape = serde_json::json!({"images": [], "output_dir": dir});
+        let (status, body) = http_request(&addr, "POST", "/download", &escape.to_string());
+        assert_eq!(status, 400, "{}", body);
+    }
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}